@@ -0,0 +1,42 @@
+// `cargo bench --bench expressions`: times the parser on a large file of
+// varied, expression-heavy statements, where nearly every token goes
+// through `match_` at each precedence level on its way down
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rlox::{Parser, Scanner};
+
+const STATEMENTS: &[&str] = &[
+    "var a{i} = (1 + 2) * 3 - 4 / 5 ~/ 6;",
+    "var b{i} = !(a{i} < 2 or a{i} >= 3 and a{i} != 4) == false;",
+    "var c{i} = a{i} > 0 ? -a{i} : a{i} & 0xff | 1 << 2 ^ ~3;",
+    "print f(a{i}, b{i}, c{i})[0].x + \"s\" + str(len([1, 2, 3]));",
+    "a{i} += b{i} * (c{i} - 1) >> 1;",
+    "if (a{i} <= b{i} and (c{i} == nil or !c{i})) print {\"k\": a{i}, \"v\": [b{i}, c{i}]};"
+];
+
+fn source(copies: usize) -> String {
+    let mut source = String::new();
+    for i in 0..copies {
+        for statement in STATEMENTS {
+            source.push_str(&statement.replace("{i}", &i.to_string()));
+            source.push('\n');
+        }
+    }
+    source
+}
+
+fn main() {
+    let runs = 20;
+    let tokens = Scanner::new(source(2000)).scan_tokens().expect("source scans");
+
+    let start = Instant::now();
+    for _ in 0..runs {
+        let (statements, errors) = Parser::new(tokens.clone()).parse();
+        assert!(errors.is_empty(), "{:?}", errors.first());
+        black_box(statements);
+    }
+    let each: Duration = start.elapsed() / runs;
+    println!("expressions {:>8} tokens {each:>12.2?} per parse", tokens.len());
+}
@@ -0,0 +1,33 @@
+// `cargo bench --bench loop`: times the tree-walker on tight loops over
+// locals, which are read by resolved slot, against the same loop over
+// globals, which are still looked up by name
+
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use rlox::{Interpreter, Parser, Resolver, Scanner};
+
+const ITERATIONS: usize = 200_000;
+
+fn time(name: &str, source: &str, runs: u32) {
+    let tokens = Scanner::new(source.to_string()).scan_tokens().expect("source scans");
+    let (mut statements, errors) = Parser::new(tokens).parse();
+    assert!(errors.is_empty(), "{:?}", errors.first());
+    Resolver::new().resolve(&mut statements);
+
+    let start = Instant::now();
+    for _ in 0..runs {
+        let mut interpreter = Interpreter::with_writer(Rc::new(RefCell::new(io::sink())));
+        interpreter.interpret(&statements, false).expect("program runs");
+    }
+    let each: Duration = start.elapsed() / runs;
+    println!("{name:<8} {ITERATIONS:>8} iterations {each:>12.2?} per run");
+}
+
+fn main() {
+    let body = "for (var i = 0; i < n; i += 1) { var x = i * 2; total = total + x - i; }";
+    time("locals", &format!("{{ var n = {ITERATIONS}; var total = 0; {{ {body} }} print total; }}"), 5);
+    time("globals", &format!("var n = {ITERATIONS}; var total = 0; {body} print total;"), 5);
+}
@@ -0,0 +1,48 @@
+// `cargo bench --bench parse`: times the parser on long left-associative
+// chains, which used to be cloned at each operator
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rlox::{Parser, Scanner};
+use rlox::token::Token;
+
+fn chain(operators: &[&str], terms: usize) -> String {
+    let mut source = String::from("print 1");
+    for i in 0..terms {
+        source.push_str(&format!(" {} {}", operators[i % operators.len()], i + 2));
+    }
+    source.push_str(";\n");
+    source
+}
+
+fn time(name: &str, tokens: &[Token], runs: u32) {
+    let start = Instant::now();
+    for _ in 0..runs {
+        let (statements, errors) = Parser::new(tokens.to_vec()).parse();
+        assert!(errors.is_empty());
+        black_box(statements);
+    }
+    let each: Duration = start.elapsed() / runs;
+    println!("{name:<12} {:>8} tokens {each:>12.2?} per parse", tokens.len());
+}
+
+fn main() {
+    let cases = [
+        ("equality", &["==", "!="][..]),
+        ("comparison", &["<", "<=", ">", ">="][..]),
+        ("term", &["+", "-"][..]),
+        ("factor", &["*", "/"][..]),
+        ("mixed", &["+", "*", "<", "==", "-", "/"][..])
+    ];
+    for (name, operators) in cases {
+        let source = chain(operators, 2000).repeat(10);
+        let tokens = Scanner::new(source).scan_tokens().expect("source scans");
+        time(name, &tokens, 20);
+    }
+
+    // unary nests rather than chains
+    let source = format!("print {}1;\n", "-".repeat(2000)).repeat(10);
+    let tokens = Scanner::new(source).scan_tokens().expect("source scans");
+    time("unary", &tokens, 20);
+}
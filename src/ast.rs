@@ -1,4 +1,4 @@
-use crate::token::Token;
+use crate::token::{Span, Token};
 use crate::token_type::TokenType;
 use strum_macros::Display;
 use std::fmt;
@@ -7,7 +7,16 @@ use std::fmt;
 pub enum Statement {
     Expression(Expression),
     Print(Expression),
-    VarDeclaration(VarDeclaration)
+    VarDeclaration(VarDeclaration),
+    If(IfStatement),
+    While(WhileStatement),
+    Switch(SwitchStatement),
+    Block(Vec<Statement>),
+    Function(FunctionDeclaration),
+    Class(ClassDeclaration),
+    Return(ReturnStatement),
+    Break(Token),
+    Continue(Token)
 }
 
 #[derive(Debug, Clone)]
@@ -16,13 +25,86 @@ pub struct VarDeclaration {
     pub initializer: Option<Expression>
 }
 
+#[derive(Debug, Clone)]
+pub struct FunctionDeclaration {
+    pub name: Token,
+    pub params: Vec<Token>,
+    // for the trailing optional parameters only, so the first
+    // `params.len() - defaults.len()` have none
+    pub defaults: Vec<Expression>,
+    // `...rest`, collecting any arguments past `params` into a list
+    pub rest: Option<Token>,
+    pub body: Vec<Statement>
+}
+
+impl FunctionDeclaration {
+    // `a b="hi" ...rest`, as the printers show the parameter list
+    fn print_params(&self) -> String {
+        let required = self.params.len() - self.defaults.len();
+        let params = self.params.iter().enumerate().map(|(i, param)| match i.checked_sub(required) {
+            Some(d) => format!("{}={}", param.lexeme, self.defaults[d].print()),
+            None => param.lexeme.clone()
+        });
+        let rest = self.rest.iter().map(|rest| format!("...{}", rest.lexeme));
+        params.chain(rest).collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassDeclaration {
+    pub name: Token,
+    pub superclass: Option<Expression>,
+    pub methods: Vec<FunctionDeclaration>
+}
+
+#[derive(Debug, Clone)]
+pub struct ReturnStatement {
+    pub keyword: Token,
+    pub value: Option<Expression>
+}
+
+#[derive(Debug, Clone)]
+pub struct IfStatement {
+    pub condition: Expression,
+    pub then_branch: Box<Statement>,
+    pub else_branch: Option<Box<Statement>>
+}
+
+// the first case whose value equals the subject runs, there's no fallthrough
+#[derive(Debug, Clone)]
+pub struct SwitchStatement {
+    pub subject: Expression,
+    pub cases: Vec<(Expression, Vec<Statement>)>,
+    pub default: Option<Vec<Statement>>
+}
+
+#[derive(Debug, Clone)]
+pub struct WhileStatement {
+    pub condition: Expression,
+    pub body: Box<Statement>,
+    // a desugared `for` increment, run after the body even on `continue`
+    pub increment: Option<Expression>
+}
+
 #[derive(Debug, Clone)]
 pub enum Expression {
     Binary(BinaryExpression),
     Grouping(GroupingExpression),
     Literal(LiteralExpression),
     Unary(UnaryExpression),
-    Variable(VarExpression)
+    Variable(VarExpression),
+    Assign(AssignExpression),
+    Logical(LogicalExpression),
+    Ternary(TernaryExpression),
+    Call(CallExpression),
+    Get(GetExpression),
+    Set(SetExpression),
+    This(ThisExpression),
+    Super(SuperExpression),
+    List(ListExpression),
+    Map(MapExpression),
+    Index(IndexExpression),
+    IndexSet(IndexSetExpression)
 }
 
 
@@ -35,12 +117,13 @@ pub struct BinaryExpression {
 
 #[derive(Debug, Clone)]
 pub struct GroupingExpression {
+    pub paren: Token,
     pub expression: Box<Expression>
 }
 
 #[derive(Debug, Clone, Display)]
 pub enum LiteralExpression {
-    Boolean(bool),
+    Boolean(Token),
     Null(Token),
     String(Token),
     Number(Token),
@@ -52,11 +135,275 @@ pub struct UnaryExpression {
     pub right: Box<Expression>
 }
 
+// Where the resolver found a local: `depth` scopes out, at position `index`
+// in that scope. Names without one are globals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize
+}
+
 #[derive(Debug, Clone)]
 pub struct VarExpression {
+    pub name: Token,
+    pub slot: Option<Slot>
+}
+
+#[derive(Debug, Clone)]
+pub struct LogicalExpression {
+    pub left: Box<Expression>,
+    pub operator: Token,
+    pub right: Box<Expression>
+}
+
+#[derive(Debug, Clone)]
+pub struct TernaryExpression {
+    pub condition: Box<Expression>,
+    pub then_expr: Box<Expression>,
+    pub else_expr: Box<Expression>
+}
+
+#[derive(Debug, Clone)]
+pub struct CallExpression {
+    pub callee: Box<Expression>,
+    pub paren: Token,
+    pub arguments: Vec<Expression>
+}
+
+#[derive(Debug, Clone)]
+pub struct GetExpression {
+    pub object: Box<Expression>,
     pub name: Token
 }
 
+#[derive(Debug, Clone)]
+pub struct SetExpression {
+    pub object: Box<Expression>,
+    pub name: Token,
+    pub value: Box<Expression>,
+    // binary operator of a compound assignment such as `obj.f += 1`
+    pub operator: Option<Token>
+}
+
+#[derive(Debug, Clone)]
+pub struct ThisExpression {
+    pub keyword: Token,
+    pub slot: Option<Slot>
+}
+
+#[derive(Debug, Clone)]
+pub struct SuperExpression {
+    pub keyword: Token,
+    pub method: Token,
+    pub slot: Option<Slot>
+}
+
+#[derive(Debug, Clone)]
+pub struct ListExpression {
+    pub bracket: Token,
+    pub elements: Vec<Expression>
+}
+
+#[derive(Debug, Clone)]
+pub struct MapExpression {
+    pub brace: Token,
+    pub entries: Vec<(Expression, Expression)>
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexExpression {
+    pub object: Box<Expression>,
+    pub bracket: Token,
+    pub index: Box<Expression>
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexSetExpression {
+    pub object: Box<Expression>,
+    pub bracket: Token,
+    pub index: Box<Expression>,
+    pub value: Box<Expression>,
+    pub operator: Option<Token>
+}
+
+#[derive(Debug, Clone)]
+pub struct AssignExpression {
+    pub name: Token,
+    pub value: Box<Expression>,
+    pub slot: Option<Slot>
+}
+
+impl Statement {
+    /// Position of a token within the statement. Statements don't keep their
+    /// leading keyword, so this is the first one they do keep; `None` for an
+    /// empty block.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Statement::Expression(e) | Statement::Print(e) => Some(e.span()),
+            Statement::VarDeclaration(v) => Some(v.name.span()),
+            Statement::If(s) => Some(s.condition.span()),
+            Statement::While(s) => Some(s.condition.span()),
+            Statement::Switch(s) => Some(s.subject.span()),
+            Statement::Block(statements) => statements.iter().find_map(|statement| statement.span()),
+            Statement::Function(f) => Some(f.name.span()),
+            Statement::Class(c) => Some(c.name.span()),
+            Statement::Return(s) => Some(s.keyword.span()),
+            Statement::Break(t) | Statement::Continue(t) => Some(t.span())
+        }
+    }
+
+    // the statement counterpart of Expression::print; a bare expression
+    // statement prints as just its expression
+    pub fn print(&self) -> String {
+        match self {
+            Statement::Expression(e) => e.print(),
+            Statement::Print(e) => format!("(print {})", e.print()),
+            Statement::VarDeclaration(v) => match &v.initializer {
+                Some(initializer) => format!("(var {} {})", v.name.lexeme, initializer.print()),
+                None => format!("(var {})", v.name.lexeme)
+            },
+            Statement::If(s) => match &s.else_branch {
+                Some(else_branch) => format!("(if {} {} {})", s.condition.print(), s.then_branch.print(), else_branch.print()),
+                None => format!("(if {} {})", s.condition.print(), s.then_branch.print())
+            },
+            Statement::While(s) => match &s.increment {
+                Some(increment) => format!("(while {} {} {})", s.condition.print(), s.body.print(), increment.print()),
+                None => format!("(while {} {})", s.condition.print(), s.body.print())
+            },
+            Statement::Switch(s) => {
+                let mut printed = format!("(switch {}", s.subject.print());
+                for (value, body) in &s.cases {
+                    printed.push(' ');
+                    printed.push_str(&Self::print_all(&format!("case {}", value.print()), body));
+                }
+                if let Some(default) = &s.default {
+                    printed.push(' ');
+                    printed.push_str(&Self::print_all("default", default));
+                }
+                printed.push(')');
+                printed
+            },
+            Statement::Block(statements) => Self::print_all("block", statements),
+            Statement::Function(f) => Self::print_function(f),
+            Statement::Class(c) => {
+                let mut s = format!("(class {}", c.name.lexeme);
+                if let Some(superclass) = &c.superclass {
+                    s.push_str(&format!(" < {}", superclass.print()));
+                }
+                for method in &c.methods {
+                    s.push(' ');
+                    s.push_str(&Self::print_function(method));
+                }
+                s.push(')');
+                s
+            },
+            Statement::Return(r) => match &r.value {
+                Some(value) => format!("(return {})", value.print()),
+                None => "(return)".to_string()
+            },
+            Statement::Break(_) => "(break)".to_string(),
+            Statement::Continue(_) => "(continue)".to_string()
+        }
+    }
+
+    // the statement counterpart of Expression::print_tree
+    pub fn print_tree(&self) -> String {
+        let mut tree = String::new();
+        self.tree_node(0, &mut tree);
+        tree
+    }
+
+    fn tree_node(&self, depth: usize, tree: &mut String) {
+        match self {
+            Statement::Expression(e) => e.tree_node(depth, tree),
+            Statement::Print(e) => {
+                tree_line(depth, "print", tree);
+                e.tree_node(depth + 1, tree);
+            },
+            Statement::VarDeclaration(v) => {
+                tree_line(depth, &format!("var {}", v.name.lexeme), tree);
+                if let Some(initializer) = &v.initializer {
+                    initializer.tree_node(depth + 1, tree);
+                }
+            },
+            Statement::If(s) => {
+                tree_line(depth, "if", tree);
+                s.condition.tree_node(depth + 1, tree);
+                s.then_branch.tree_node(depth + 1, tree);
+                if let Some(else_branch) = &s.else_branch {
+                    else_branch.tree_node(depth + 1, tree);
+                }
+            },
+            Statement::While(s) => {
+                tree_line(depth, "while", tree);
+                s.condition.tree_node(depth + 1, tree);
+                s.body.tree_node(depth + 1, tree);
+                if let Some(increment) = &s.increment {
+                    increment.tree_node(depth + 1, tree);
+                }
+            },
+            Statement::Switch(s) => {
+                tree_line(depth, "switch", tree);
+                s.subject.tree_node(depth + 1, tree);
+                for (value, body) in &s.cases {
+                    tree_line(depth + 1, "case", tree);
+                    value.tree_node(depth + 2, tree);
+                    for statement in body {
+                        statement.tree_node(depth + 2, tree);
+                    }
+                }
+                if let Some(default) = &s.default {
+                    Self::tree_all(depth + 1, "default", default, tree);
+                }
+            },
+            Statement::Block(statements) => Self::tree_all(depth, "block", statements, tree),
+            Statement::Function(f) => Self::tree_function(depth, f, tree),
+            Statement::Class(c) => {
+                match &c.superclass {
+                    Some(superclass) => tree_line(depth, &format!("class {} < {}", c.name.lexeme, superclass.print()), tree),
+                    None => tree_line(depth, &format!("class {}", c.name.lexeme), tree)
+                }
+                for method in &c.methods {
+                    Self::tree_function(depth + 1, method, tree);
+                }
+            },
+            Statement::Return(r) => {
+                tree_line(depth, "return", tree);
+                if let Some(value) = &r.value {
+                    value.tree_node(depth + 1, tree);
+                }
+            },
+            Statement::Break(_) => tree_line(depth, "break", tree),
+            Statement::Continue(_) => tree_line(depth, "continue", tree)
+        }
+    }
+
+    fn tree_function(depth: usize, function: &FunctionDeclaration, tree: &mut String) {
+        Self::tree_all(depth, &format!("fun {}({})", function.name.lexeme, function.print_params()), &function.body, tree);
+    }
+
+    fn tree_all(depth: usize, name: &str, statements: &[Statement], tree: &mut String) {
+        tree_line(depth, name, tree);
+        for statement in statements {
+            statement.tree_node(depth + 1, tree);
+        }
+    }
+
+    fn print_function(function: &FunctionDeclaration) -> String {
+        Self::print_all(&format!("fun {}({})", function.name.lexeme, function.print_params()), &function.body)
+    }
+
+    fn print_all(name: &str, statements: &[Statement]) -> String {
+        let mut s = format!("({name}");
+        for statement in statements {
+            s.push(' ');
+            s.push_str(&statement.print());
+        }
+        s.push(')');
+        s
+    }
+}
+
 impl Expression {
     pub fn binary(left: Expression, operator: Token, right: Expression) -> Self {
         Self::Binary(BinaryExpression {
@@ -67,8 +414,96 @@ impl Expression {
         )
     }
 
-    pub fn grouping(expression: Expression) -> Self {
+    pub fn logical(left: Expression, operator: Token, right: Expression) -> Self {
+        Self::Logical(LogicalExpression {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right)
+        })
+    }
+
+    pub fn ternary(condition: Expression, then_expr: Expression, else_expr: Expression) -> Self {
+        Self::Ternary(TernaryExpression {
+            condition: Box::new(condition),
+            then_expr: Box::new(then_expr),
+            else_expr: Box::new(else_expr)
+        })
+    }
+
+    pub fn call(callee: Expression, paren: Token, arguments: Vec<Expression>) -> Self {
+        Self::Call(CallExpression {
+            callee: Box::new(callee),
+            paren,
+            arguments
+        })
+    }
+
+    pub fn get(object: Expression, name: Token) -> Self {
+        Self::Get(GetExpression {
+            object: Box::new(object),
+            name
+        })
+    }
+
+    pub fn set(object: Expression, name: Token, value: Expression, operator: Option<Token>) -> Self {
+        Self::Set(SetExpression {
+            object: Box::new(object),
+            name,
+            value: Box::new(value),
+            operator
+        })
+    }
+
+    pub fn this(keyword: Token) -> Self {
+        Self::This(ThisExpression {
+            keyword,
+            slot: None
+        })
+    }
+
+    pub fn super_(keyword: Token, method: Token) -> Self {
+        Self::Super(SuperExpression {
+            keyword,
+            method,
+            slot: None
+        })
+    }
+
+    pub fn list(bracket: Token, elements: Vec<Expression>) -> Self {
+        Self::List(ListExpression {
+            bracket,
+            elements
+        })
+    }
+
+    pub fn map(brace: Token, entries: Vec<(Expression, Expression)>) -> Self {
+        Self::Map(MapExpression {
+            brace,
+            entries
+        })
+    }
+
+    pub fn index(object: Expression, bracket: Token, index: Expression) -> Self {
+        Self::Index(IndexExpression {
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index)
+        })
+    }
+
+    pub fn index_set(object: Expression, bracket: Token, index: Expression, value: Expression, operator: Option<Token>) -> Self {
+        Self::IndexSet(IndexSetExpression {
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+            value: Box::new(value),
+            operator
+        })
+    }
+
+    pub fn grouping(paren: Token, expression: Expression) -> Self {
         Self::Grouping(GroupingExpression{
+            paren,
             expression: Box::new(expression)
         })
     }
@@ -82,14 +517,22 @@ impl Expression {
 
     pub fn variable(token: Token) -> Self {
         Self::Variable(VarExpression {
-            name: token
+            name: token,
+            slot: None
+        })
+    }
+
+    pub fn assign(name: Token, value: Expression) -> Self {
+        Self::Assign(AssignExpression {
+            name,
+            value: Box::new(value),
+            slot: None
         })
     }
 
     pub fn literal(token: Token) -> Self {
         match token.token_type {
-            TokenType::TRUE => Self::Literal(LiteralExpression::Boolean(true)),
-            TokenType::FALSE => Self::Literal(LiteralExpression::Boolean(false)),
+            TokenType::TRUE | TokenType::FALSE => Self::Literal(LiteralExpression::Boolean(token)),
             TokenType::NIL => Self::Literal(LiteralExpression::Null(token)),
             TokenType::STRING => Self::Literal(LiteralExpression::String(token)),
             TokenType::NUMBER => Self::Literal(LiteralExpression::Number(token)),
@@ -97,6 +540,35 @@ impl Expression {
         }
     }
 
+    /// Position of the expression's first token, so errors can point at the
+    /// exact subexpression responsible.
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Binary(e) => e.left.span(),
+            Expression::Grouping(e) => e.paren.span(),
+            Expression::Literal(e) => {
+                match e {
+                    LiteralExpression::Boolean(t) | LiteralExpression::Null(t) |
+                    LiteralExpression::String(t) | LiteralExpression::Number(t) => t.span()
+                }
+            },
+            Expression::Unary(e) => e.operator.span(),
+            Expression::Variable(v) => v.name.span(),
+            Expression::Assign(a) => a.name.span(),
+            Expression::Logical(e) => e.left.span(),
+            Expression::Ternary(e) => e.condition.span(),
+            Expression::Call(e) => e.callee.span(),
+            Expression::Get(e) => e.object.span(),
+            Expression::Set(e) => e.object.span(),
+            Expression::This(e) => e.keyword.span(),
+            Expression::Super(e) => e.keyword.span(),
+            Expression::List(e) => e.bracket.span(),
+            Expression::Map(e) => e.brace.span(),
+            Expression::Index(e) => e.object.span(),
+            Expression::IndexSet(e) => e.object.span()
+        }
+    }
+
     pub fn parenthesize(&self, name: String, expressions: Vec<Expression>) -> String {
         let mut s = String::new();
 
@@ -104,22 +576,7 @@ impl Expression {
         s.push_str(&name);
         for expression in expressions {
             s.push(' ');
-            let part = match expression {
-                        Expression::Binary(e) => self.parenthesize(e.operator.lexeme.clone(), vec![*e.left.clone(), *e.right.clone()]),
-                        Expression::Grouping(e) => self.parenthesize("group".to_string(), vec![*e.expression.clone()]),
-                        Expression::Literal(e) => { 
-                            match e { 
-                                LiteralExpression::Null(_) => "nil".to_string(), 
-                                LiteralExpression::Boolean(b) => b.to_string(), 
-                                LiteralExpression::Number(t) => t.to_string(), 
-                                LiteralExpression::String(t) => t.to_string()
-                            }
-                        },
-                        Expression::Unary(e) => self.parenthesize(e.operator.lexeme.clone(), vec![*e.right.clone()]),
-                        Expression::Variable(v) => v.name.to_string()
-
-                };
-            s.push_str(&part);
+            s.push_str(&expression.print());
         }
         s.push(')');
         s
@@ -132,14 +589,141 @@ impl Expression {
             Expression::Literal(e) => { 
                 match e { 
                     LiteralExpression::Null(_) => "nil".to_string(), 
-                    LiteralExpression::Boolean(b) => b.to_string(), 
-                    LiteralExpression::Number(t) => t.to_string(), 
-                    LiteralExpression::String(t) => t.to_string()
+                    LiteralExpression::Boolean(t) => t.lexeme.clone(), 
+                    LiteralExpression::Number(t) | LiteralExpression::String(t) => t.lexeme.clone()
                 }
             },
             Expression::Unary(e) => self.parenthesize(e.operator.lexeme.clone(), vec![*e.right.clone()]),
-            Expression::Variable(v) => v.name.to_string()
+            Expression::Variable(v) => v.name.lexeme.clone(),
+            Expression::Assign(a) => self.parenthesize(format!("= {}", a.name.lexeme), vec![*a.value.clone()]),
+            Expression::Logical(e) => self.parenthesize(e.operator.lexeme.clone(), vec![*e.left.clone(), *e.right.clone()]),
+            Expression::Ternary(e) => self.parenthesize("?:".to_string(), vec![*e.condition.clone(), *e.then_expr.clone(), *e.else_expr.clone()]),
+            Expression::Call(e) => self.parenthesize("call".to_string(), [vec![*e.callee.clone()], e.arguments.clone()].concat()),
+            Expression::Get(e) => self.parenthesize(format!(". {}", e.name.lexeme), vec![*e.object.clone()]),
+            Expression::Set(e) => self.parenthesize(format!(".{}= {}", Self::compound(&e.operator), e.name.lexeme), vec![*e.object.clone(), *e.value.clone()]),
+            Expression::This(_) => "this".to_string(),
+            Expression::Super(e) => format!("super.{}", e.method.lexeme),
+            Expression::List(e) => self.parenthesize("list".to_string(), e.elements.clone()),
+            Expression::Map(e) => self.parenthesize("map".to_string(), e.entries.iter().flat_map(|(k, v)| [k.clone(), v.clone()]).collect()),
+            Expression::Index(e) => self.parenthesize("[]".to_string(), vec![*e.object.clone(), *e.index.clone()]),
+            Expression::IndexSet(e) => self.parenthesize(format!("[]{}=", Self::compound(&e.operator)), vec![*e.object.clone(), *e.index.clone(), *e.value.clone()])
+        }
+    }
+
+    // postfix notation: `(1 + 2) * 3` prints as `1 2 + 3 *`
+    pub fn print_rpn(&self) -> String {
+        match self {
+            Expression::Binary(e) => Self::postfix(&[&e.left, &e.right], &e.operator.lexeme),
+            Expression::Grouping(e) => e.expression.print_rpn(),
+            Expression::Literal(e) => {
+                match e {
+                    LiteralExpression::Null(_) => "nil".to_string(),
+                    LiteralExpression::Boolean(t) => t.lexeme.clone(),
+                    LiteralExpression::Number(t) | LiteralExpression::String(t) => t.lexeme.clone()
+                }
+            },
+            Expression::Unary(e) => Self::postfix(&[&e.right], &e.operator.lexeme),
+            Expression::Variable(v) => v.name.lexeme.clone(),
+            Expression::Assign(a) => Self::postfix(&[&a.value], &format!("{} =", a.name.lexeme)),
+            Expression::Logical(e) => Self::postfix(&[&e.left, &e.right], &e.operator.lexeme),
+            Expression::Ternary(e) => Self::postfix(&[&e.condition, &e.then_expr, &e.else_expr], "?:"),
+            Expression::Call(e) => {
+                let operands = [&*e.callee].into_iter().chain(&e.arguments).collect::<Vec<_>>();
+                Self::postfix(&operands, "call")
+            },
+            Expression::Get(e) => Self::postfix(&[&e.object], &format!("{} .", e.name.lexeme)),
+            Expression::Set(e) => Self::postfix(&[&e.object, &e.value], &format!("{} .{}=", e.name.lexeme, Self::compound(&e.operator))),
+            Expression::This(_) => "this".to_string(),
+            Expression::Super(e) => format!("super.{}", e.method.lexeme),
+            Expression::List(e) => Self::postfix(&e.elements.iter().collect::<Vec<_>>(), "list"),
+            Expression::Map(e) => {
+                let operands = e.entries.iter().flat_map(|(k, v)| [k, v]).collect::<Vec<_>>();
+                Self::postfix(&operands, "map")
+            },
+            Expression::Index(e) => Self::postfix(&[&e.object, &e.index], "[]"),
+            Expression::IndexSet(e) => Self::postfix(&[&e.object, &e.index, &e.value], &format!("[]{}=", Self::compound(&e.operator)))
+        }
+    }
+
+    // a Graphviz digraph with one node per expression, edges labelled by
+    // the child's role
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph ast {\n");
+        self.dot_node(&mut 0, &mut dot);
+        dot.push_str("}\n");
+        dot
+    }
+
+    // one indented line per node, children two spaces deeper than their parent:
+    //   *
+    //     group
+    //       +
+    //         1
+    //         2
+    //     3
+    pub fn print_tree(&self) -> String {
+        let mut tree = String::new();
+        self.tree_node(0, &mut tree);
+        tree
+    }
+
+    fn tree_node(&self, depth: usize, tree: &mut String) {
+        let (label, children) = self.node();
+        tree_line(depth, &label, tree);
+        for (_, child) in children {
+            child.tree_node(depth + 1, tree);
+        }
+    }
+
+    fn dot_node(&self, next_id: &mut usize, dot: &mut String) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+
+        let (label, children) = self.node();
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+        dot.push_str(&format!("  n{id} [label=\"{label}\"];\n"));
+        for (role, child) in children {
+            let child_id = child.dot_node(next_id, dot);
+            dot.push_str(&format!("  n{id} -> n{child_id} [label=\"{role}\"];\n"));
         }
+        id
+    }
+
+    // a node's label and its children, each tagged with the role it plays
+    fn node(&self) -> (String, Vec<(&str, &Expression)>) {
+        match self {
+            Expression::Binary(e) => (e.operator.lexeme.clone(), vec![("left", &e.left), ("right", &e.right)]),
+            Expression::Grouping(e) => ("group".to_string(), vec![("operand", &e.expression)]),
+            Expression::Literal(_) => (self.print_rpn(), vec![]),
+            Expression::Unary(e) => (e.operator.lexeme.clone(), vec![("operand", &e.right)]),
+            Expression::Variable(v) => (v.name.lexeme.clone(), vec![]),
+            Expression::Assign(a) => (format!("{} =", a.name.lexeme), vec![("value", &a.value)]),
+            Expression::Logical(e) => (e.operator.lexeme.clone(), vec![("left", &e.left), ("right", &e.right)]),
+            Expression::Ternary(e) => ("?:".to_string(), vec![("condition", &e.condition), ("then", &e.then_expr), ("else", &e.else_expr)]),
+            Expression::Call(e) => {
+                let arguments = e.arguments.iter().map(|argument| ("argument", argument));
+                ("call".to_string(), [("callee", &*e.callee)].into_iter().chain(arguments).collect())
+            },
+            Expression::Get(e) => (format!(". {}", e.name.lexeme), vec![("object", &e.object)]),
+            Expression::Set(e) => (format!(".{}= {}", Self::compound(&e.operator), e.name.lexeme), vec![("object", &e.object), ("value", &e.value)]),
+            Expression::This(_) => ("this".to_string(), vec![]),
+            Expression::Super(e) => (format!("super.{}", e.method.lexeme), vec![]),
+            Expression::List(e) => ("list".to_string(), e.elements.iter().map(|element| ("element", element)).collect()),
+            Expression::Map(e) => ("map".to_string(), e.entries.iter().flat_map(|(k, v)| [("key", k), ("value", v)]).collect()),
+            Expression::Index(e) => ("[]".to_string(), vec![("object", &e.object), ("index", &e.index)]),
+            Expression::IndexSet(e) => (format!("[]{}=", Self::compound(&e.operator)), vec![("object", &e.object), ("index", &e.index), ("value", &e.value)])
+        }
+    }
+
+    fn postfix(operands: &[&Expression], operator: &str) -> String {
+        let mut parts = operands.iter().map(|operand| operand.print_rpn()).collect::<Vec<_>>();
+        parts.push(operator.to_string());
+        parts.join(" ")
+    }
+
+    // the `+` of a `+=` target, empty for plain assignment
+    fn compound(operator: &Option<Token>) -> &str {
+        operator.as_ref().map_or("", |operator| operator.lexeme.as_str())
     }
 }
 
@@ -147,4 +731,10 @@ impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.print())
     }
-}
\ No newline at end of file
+}
+
+fn tree_line(depth: usize, label: &str, tree: &mut String) {
+    tree.push_str(&"  ".repeat(depth));
+    tree.push_str(label);
+    tree.push('\n');
+}
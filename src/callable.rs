@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use crate::ast::FunctionDeclaration;
+use crate::environment::Environment;
+use crate::interpreter::{RuntimeError, Value};
+
+pub type NativeFn = dyn Fn(Vec<Value>) -> Result<Value, RuntimeError>;
+
+/// How many arguments a callable takes; `min` and `max` are the same unless
+/// some are optional, and there's no `max` with a rest parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>
+}
+
+impl Arity {
+    pub fn of(declaration: &FunctionDeclaration) -> Self {
+        let params = declaration.params.len();
+        let required = params - declaration.defaults.len();
+        match declaration.rest {
+            Some(_) => Self::at_least(required),
+            None => Self::from(required..=params)
+        }
+    }
+
+    pub fn at_least(min: usize) -> Self {
+        Self { min, max: None }
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(count: usize) -> Self {
+        Self { min: count, max: Some(count) }
+    }
+}
+
+impl From<RangeInclusive<usize>> for Arity {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        Self { min: *range.start(), max: Some(*range.end()) }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{max}"),
+            Some(max) => write!(f, "{} to {max}", self.min),
+            None => write!(f, "at least {}", self.min)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: Arity,
+    pub func: Rc<NativeFn>
+}
+
+impl NativeFunction {
+    pub fn new(name: &str, arity: impl Into<Arity>, func: impl Fn(Vec<Value>) -> Result<Value, RuntimeError> + 'static) -> Self {
+        Self { name: name.to_string(), arity: arity.into(), func: Rc::new(func) }
+    }
+
+    pub fn call(&self, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        (self.func)(arguments)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub struct LoxFunction {
+    pub declaration: Rc<FunctionDeclaration>,
+    pub closure: Rc<RefCell<Environment>>,
+    pub is_initializer: bool
+}
+
+impl LoxFunction {
+    pub fn new(declaration: FunctionDeclaration, closure: Rc<RefCell<Environment>>, is_initializer: bool) -> Self {
+        Self {
+            declaration: Rc::new(declaration),
+            closure,
+            is_initializer
+        }
+    }
+
+    pub fn bind(&self, instance: Value) -> Self {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+        environment.define_slot(instance);
+        Self {
+            declaration: Rc::clone(&self.declaration),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
+        Arity::of(&self.declaration)
+    }
+}
+
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoxFunction")
+            .field("name", &self.declaration.name.lexeme)
+            .field("arity", &self.arity())
+            .finish()
+    }
+}
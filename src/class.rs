@@ -0,0 +1,81 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use crate::callable::{Arity, LoxFunction};
+use crate::interpreter::Value;
+
+pub struct LoxClass {
+    pub name: String,
+    pub superclass: Option<Rc<LoxClass>>,
+    pub methods: HashMap<String, LoxFunction>
+}
+
+impl LoxClass {
+    pub fn new(name: String, superclass: Option<Rc<LoxClass>>, methods: HashMap<String, LoxFunction>) -> Self {
+        Self {
+            name,
+            superclass,
+            methods
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<LoxFunction> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref().and_then(|superclass| superclass.find_method(name))
+        }
+    }
+
+    // calling the class takes the arguments of its `init`, if any
+    pub fn arity(&self) -> Arity {
+        self.find_method("init").map_or(Arity::from(0), |init| init.arity())
+    }
+}
+
+impl fmt::Debug for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoxClass")
+            .field("name", &self.name)
+            .field("superclass", &self.superclass.as_ref().map(|superclass| &superclass.name))
+            .finish()
+    }
+}
+
+pub struct LoxInstance {
+    pub class: Rc<LoxClass>,
+    pub fields: HashMap<String, Value>
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new()
+        }
+    }
+
+    // fields shadow methods of the same name; methods come back bound to
+    // the instance so `this` refers to it
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, name: &str) -> Option<Value> {
+        let instance_ref = instance.borrow();
+        match instance_ref.fields.get(name) {
+            Some(value) => Some(value.clone()),
+            None => instance_ref.class.find_method(name)
+                .map(|method| Value::Function(method.bind(Value::Instance(Rc::clone(instance)))))
+        }
+    }
+
+    pub fn set(&mut self, name: String, value: Value) {
+        self.fields.insert(name, value);
+    }
+}
+
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoxInstance")
+            .field("class", &self.class.name)
+            .field("fields", &self.fields)
+            .finish()
+    }
+}
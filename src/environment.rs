@@ -1,26 +1,161 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
-use crate::interpreter::{Value, InterpreterError};
+use std::rc::Rc;
+use crate::interner::Symbol;
+use crate::interpreter::Value;
+use crate::ast::Slot;
 use crate::token::Token;
 
+#[derive(Debug)]
+pub enum EnvironmentError {
+    UndefinedVariable { name: String, line: usize, column: usize }
+}
 
+// Globals (and natives) are looked up by name; every other scope holds its
+// variables in declaration order, matching the slots the resolver assigns.
 pub struct Environment {
-    pub values: HashMap<String, Value> 
+    pub values: HashMap<Symbol, Value>,
+    slots: Vec<Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Environment {
     pub fn new() -> Self {
         Self {
-            values: HashMap::new()
+            values: HashMap::new(),
+            slots: Vec::new(),
+            enclosing: None
         }
     }
-    pub fn define(&mut self, name: String, value: Value) {
+
+    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            slots: Vec::new(),
+            enclosing: Some(enclosing)
+        }
+    }
+
+    pub fn define(&mut self, name: Symbol, value: Value) {
         self.values.insert(name, value);
     }
 
-    pub fn get(&self, token: Token) -> Result<Value, InterpreterError>  {
-        match self.values.get(&token.lexeme) {
+    pub fn define_slot(&mut self, value: Value) {
+        self.slots.push(value);
+    }
+
+    // unlike `get`, stays in this scope: names are only assigned at the
+    // globals, whose enclosing scope holds the natives, which user code may
+    // shadow but not replace
+    pub fn assign(&mut self, token: &Token, value: Value) -> Result<Value, EnvironmentError> {
+        match self.values.get_mut(&token.symbol()) {
+            Some(val) => {*val = value.clone(); Ok(value)},
+            None => Err(EnvironmentError::UndefinedVariable { name: token.lexeme.clone(), line: token.line, column: token.column })
+        }
+    }
+
+    pub fn get(&self, token: &Token) -> Result<Value, EnvironmentError>  {
+        match self.values.get(&token.symbol()) {
             Some(val) => Ok(val.clone()),
-            None => {let name = &token.lexeme; println!("Undefined variable '{name}'."); Err(InterpreterError)}
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow().get(token),
+                None => Err(EnvironmentError::UndefinedVariable { name: token.lexeme.clone(), line: token.line, column: token.column })
+            }
+        }
+    }
+
+    pub fn get_at(&self, slot: Slot, token: &Token) -> Result<Value, EnvironmentError> {
+        if slot.depth == 0 {
+            return match self.slots.get(slot.index) {
+                Some(val) => Ok(val.clone()),
+                None => Err(EnvironmentError::UndefinedVariable { name: token.lexeme.clone(), line: token.line, column: token.column })
+            }
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get_at(Slot { depth: slot.depth - 1, ..slot }, token),
+            None => Err(EnvironmentError::UndefinedVariable { name: token.lexeme.clone(), line: token.line, column: token.column })
+        }
+    }
+
+    pub fn assign_at(&mut self, slot: Slot, token: &Token, value: Value) -> Result<Value, EnvironmentError> {
+        if slot.depth == 0 {
+            return match self.slots.get_mut(slot.index) {
+                Some(val) => {*val = value.clone(); Ok(value)},
+                None => Err(EnvironmentError::UndefinedVariable { name: token.lexeme.clone(), line: token.line, column: token.column })
+            }
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(Slot { depth: slot.depth - 1, ..slot }, token, value),
+            None => Err(EnvironmentError::UndefinedVariable { name: token.lexeme.clone(), line: token.line, column: token.column })
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interner;
+    use crate::token_type::TokenType;
+
+    fn name(lexeme: &str) -> Token {
+        Token::new(TokenType::IDENTIFIER, lexeme.to_string(), None, 3, 7)
+    }
+
+    #[test]
+    fn get_finds_enclosing_definitions() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define(interner::intern("a"), Value::Number(1.0));
+        let inner = Environment::new_enclosed(globals);
+        assert!(matches!(inner.get(&name("a")), Ok(Value::Number(n)) if n == 1.0));
+    }
+
+    #[test]
+    fn inner_definitions_shadow_outer_ones() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define(interner::intern("a"), Value::Number(1.0));
+        let mut inner = Environment::new_enclosed(Rc::clone(&globals));
+        inner.define(interner::intern("a"), Value::Number(2.0));
+        assert!(matches!(inner.get(&name("a")), Ok(Value::Number(n)) if n == 2.0));
+        assert!(matches!(globals.borrow().get(&name("a")), Ok(Value::Number(n)) if n == 1.0));
+    }
+
+    #[test]
+    fn assign_updates_a_definition_in_this_scope() {
+        let mut globals = Environment::new();
+        globals.define(interner::intern("a"), Value::Number(1.0));
+        assert!(globals.assign(&name("a"), Value::String("two".to_string())).is_ok());
+        assert!(matches!(globals.get(&name("a")), Ok(Value::String(s)) if s == "two"));
+    }
+
+    #[test]
+    fn assign_does_not_reach_enclosing_scopes() {
+        let natives = Rc::new(RefCell::new(Environment::new()));
+        natives.borrow_mut().define(interner::intern("a"), Value::Number(1.0));
+        let mut globals = Environment::new_enclosed(Rc::clone(&natives));
+        assert!(globals.assign(&name("a"), Value::Null).is_err());
+        assert!(matches!(natives.borrow().get(&name("a")), Ok(Value::Number(n)) if n == 1.0));
+    }
+
+    #[test]
+    fn assigning_an_undeclared_name_is_an_error() {
+        let mut inner = Environment::new_enclosed(Rc::new(RefCell::new(Environment::new())));
+        assert!(inner.assign(&name("a"), Value::Number(1.0)).is_err());
+    }
+
+    #[test]
+    fn get_of_an_undefined_name_is_an_error() {
+        let inner = Environment::new_enclosed(Rc::new(RefCell::new(Environment::new())));
+        match inner.get(&name("missing")) {
+            Err(EnvironmentError::UndefinedVariable { name, line, column }) => assert_eq!((name.as_str(), line, column), ("missing", 3, 7)),
+            other => panic!("expected an undefined variable, got {other:?}")
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+/// An interned identifier: the same name always gets the same symbol, so
+/// environments can key their variables by it rather than by a `String`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+// One table for the whole process rather than one per scanner: the REPL scans
+// each line separately, and a name must get the same symbol every time. Names
+// are leaked so `resolve` can hand them out without holding the lock; there's
+// one per distinct identifier, however often it occurs.
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    names: Vec<&'static str>
+}
+
+static INTERNER: LazyLock<Mutex<Interner>> = LazyLock::new(Mutex::default);
+
+/// The symbol for `name`, adding it to the table the first time it's seen.
+pub fn intern(name: &str) -> Symbol {
+    let mut interner = INTERNER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(symbol) = interner.symbols.get(name) {
+        return *symbol
+    }
+    let symbol = Symbol(interner.names.len() as u32);
+    let name: &'static str = Box::leak(name.into());
+    interner.names.push(name);
+    interner.symbols.insert(name, symbol);
+    symbol
+}
+
+/// The name `symbol` was interned from.
+pub fn resolve(symbol: Symbol) -> &'static str {
+    let interner = INTERNER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    interner.names[symbol.0 as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_same_name_gets_the_same_symbol() {
+        assert_eq!(intern("interned_twice"), intern(&String::from("interned_twice")));
+        assert_ne!(intern("interned_a"), intern("interned_b"));
+    }
+
+    #[test]
+    fn resolve_gives_back_the_name() {
+        assert_eq!(resolve(intern("resolved")), "resolved");
+    }
+}
@@ -1,17 +1,288 @@
-use crate::{ast::{BinaryExpression, Expression, GroupingExpression, LiteralExpression, Statement, UnaryExpression, VarExpression}, environment::Environment};
-use strum_macros::Display;
+use crate::{ast::{AssignExpression, ClassDeclaration, FunctionDeclaration, IfStatement, ReturnStatement, SwitchStatement, VarDeclaration, WhileStatement, Slot, BinaryExpression, Expression, GroupingExpression, LiteralExpression, LogicalExpression, Statement, TernaryExpression, CallExpression, GetExpression, IndexExpression, IndexSetExpression, ListExpression, MapExpression, SetExpression, SuperExpression, ThisExpression, UnaryExpression, VarExpression}, environment::{Environment, EnvironmentError}};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use crate::interner::{self, Symbol};
+use crate::token::{Span, Token};
 use crate::token_type::{Literal, TokenType};
+use crate::callable::{Arity, LoxFunction, NativeFunction};
+use crate::class::{LoxClass, LoxInstance};
+use crate::natives::{self, StdinReader};
+use crate::vm::Function;
 
-#[derive(Display, Debug, Clone)]
+#[derive(Clone)]
 pub enum Value {
     Boolean(bool),
     Null,
     String(String),
-    Number(f32)
+    Integer(i64),
+    Number(f64),
+    NativeFunction(NativeFunction),
+    Function(LoxFunction),
+    // compiled by the VM
+    VmFunction(Rc<Function>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<HashMap<String, Value>>>)
+}
+
+// Numbers follow IEEE 754 like the book, so NaN is unequal to itself and
+// `!=` is its exact negation. Integers compare equal to the same float, and
+// functions, classes, instances, lists and maps by identity.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Number(n1), Self::Number(n2)) => n1 == n2,
+            (Self::Integer(i1), Self::Integer(i2)) => i1 == i2,
+            (Self::Integer(i), Self::Number(n)) => (*i as f64) == *n,
+            (Self::Number(n), Self::Integer(i)) => *n == (*i as f64),
+            (Self::Boolean(b1), Self::Boolean(b2)) => b1 == b2,
+            (Self::String(s1), Self::String(s2)) => s1 == s2,
+            (Self::Null, Self::Null) => true,
+            (Self::NativeFunction(f1), Self::NativeFunction(f2)) => Rc::ptr_eq(&f1.func, &f2.func),
+            (Self::Function(f1), Self::Function(f2)) => Rc::ptr_eq(&f1.declaration, &f2.declaration) && Rc::ptr_eq(&f1.closure, &f2.closure),
+            (Self::VmFunction(f1), Self::VmFunction(f2)) => Rc::ptr_eq(f1, f2),
+            (Self::Class(c1), Self::Class(c2)) => Rc::ptr_eq(c1, c2),
+            (Self::Instance(i1), Self::Instance(i2)) => Rc::ptr_eq(i1, i2),
+            (Self::List(l1), Self::List(l2)) => Rc::ptr_eq(l1, l2),
+            (Self::Map(m1), Self::Map(m2)) => Rc::ptr_eq(m1, m2),
+            (_, _) => false
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Integer(i) => write!(f, "{i}"),
+            Self::Number(n) => {if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {write!(f, "{}", *n as i64)} else {write!(f, "{n}")}},
+            Self::Boolean(b) => write!(f, "{b}"),
+            Self::String(s) => write!(f, "{s}"),
+            Self::Null => write!(f, "nil"),
+            Self::NativeFunction(_) => write!(f, "<native fn>"),
+            Self::Function(func) => write!(f, "<fn {}>", func.declaration.name.lexeme),
+            Self::VmFunction(func) => write!(f, "<fn {}>", func.name),
+            Self::Class(c) => write!(f, "{}", c.name),
+            Self::Instance(i) => write!(f, "{} instance", i.borrow().class.name),
+            Self::List(l) => format_once(Rc::as_ptr(l).cast(), f, "[...]", |f| {
+                write!(f, "[{}]", l.borrow().iter().map(Value::to_string).collect::<Vec<_>>().join(", "))
+            }),
+            Self::Map(m) => format_once(Rc::as_ptr(m).cast(), f, "{...}", |f| {
+                // sorted so that output doesn't depend on hash order
+                let map = m.borrow();
+                let mut entries = map.iter().map(|(k, v)| format!("{k}: {v}")).collect::<Vec<_>>();
+                entries.sort();
+                write!(f, "{{{}}}", entries.join(", "))
+            })
+        }
+    }
+}
+
+// the variant and its contents, for inspecting values rather than showing
+// them to the user
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Boolean(b) => f.debug_tuple("Boolean").field(b).finish(),
+            Self::Null => write!(f, "Null"),
+            Self::String(s) => f.debug_tuple("String").field(s).finish(),
+            Self::Integer(i) => f.debug_tuple("Integer").field(i).finish(),
+            Self::Number(n) => f.debug_tuple("Number").field(n).finish(),
+            Self::NativeFunction(func) => f.debug_tuple("NativeFunction").field(func).finish(),
+            Self::Function(func) => f.debug_tuple("Function").field(func).finish(),
+            Self::VmFunction(func) => f.debug_tuple("VmFunction").field(func).finish(),
+            Self::Class(c) => f.debug_tuple("Class").field(c).finish(),
+            Self::Instance(i) => format_once(Rc::as_ptr(i).cast(), f, "Instance(...)", |f| {
+                f.debug_tuple("Instance").field(&i.borrow()).finish()
+            }),
+            Self::List(l) => format_once(Rc::as_ptr(l).cast(), f, "List([...])", |f| {
+                f.debug_tuple("List").field(&l.borrow()).finish()
+            }),
+            Self::Map(m) => format_once(Rc::as_ptr(m).cast(), f, "Map({...})", |f| {
+                f.debug_tuple("Map").field(&m.borrow()).finish()
+            })
+        }
+    }
+}
+
+thread_local! {
+    // the lists, maps and instances being formatted, outermost first, so one containing
+    // itself is cut short where it recurs rather than overflowing the stack
+    static FORMATTING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+// writes the value at `pointer` with `format`, or `cycle` if it's already
+// being formatted further out
+fn format_once(pointer: *const (), f: &mut fmt::Formatter, cycle: &str, format: impl FnOnce(&mut fmt::Formatter) -> fmt::Result) -> fmt::Result {
+    if FORMATTING.with_borrow(|formatting| formatting.contains(&pointer)) {
+        return write!(f, "{cycle}")
+    }
+    FORMATTING.with_borrow_mut(|formatting| formatting.push(pointer));
+    let result = format(f);
+    FORMATTING.with_borrow_mut(|formatting| formatting.pop());
+    result
+}
+
+// What went wrong at runtime. Where it went wrong is kept alongside in
+// InterpreterError, since Value operations don't know their position.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    TypeMismatch(&'static str),
+    DivisionByZero,
+    InvalidShift(&'static str),
+    UndefinedVariable { name: String },
+    UndefinedProperty { name: String },
+    AssignToNative { name: String },
+    ArityMismatch { expected: Arity, got: usize },
+    NotCallable,
+    NoProperties,
+    NoFields,
+    NotIndexable,
+    IndexOutOfBounds,
+    SuperclassNotClass,
+    StackOverflow,
+    EmptyList,
+    InvalidNumber { text: String },
+    // the error message is kept, io::Error being neither Clone nor PartialEq
+    Output(String),
+    Input(String),
+    // `assert`, with the message it was given if any
+    AssertionFailed(Option<String>),
+    // errors the resolver or parser should already have ruled out
+    Other(String)
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::TypeMismatch(message) | RuntimeError::InvalidShift(message) => write!(f, "{message}"),
+            RuntimeError::DivisionByZero => write!(f, "Division by zero."),
+            RuntimeError::UndefinedVariable { name } => write!(f, "Undefined variable '{name}'."),
+            RuntimeError::UndefinedProperty { name } => write!(f, "Undefined property '{name}'."),
+            RuntimeError::AssignToNative { name } => write!(f, "Can't assign to native function '{name}'."),
+            RuntimeError::ArityMismatch { expected, got } => {
+                // "1 argument" and "at least 1 argument", but "0 to 1 arguments"
+                let noun = if expected.min == 1 && expected.max.is_none_or(|max| max == 1) { "argument" } else { "arguments" };
+                write!(f, "Expected {expected} {noun} but got {got}.")
+            },
+            RuntimeError::NotCallable => write!(f, "Can only call functions and classes."),
+            RuntimeError::NoProperties => write!(f, "Only instances have properties."),
+            RuntimeError::NoFields => write!(f, "Only instances have fields."),
+            RuntimeError::NotIndexable => write!(f, "Only lists and maps can be indexed."),
+            RuntimeError::IndexOutOfBounds => write!(f, "List index out of bounds."),
+            RuntimeError::SuperclassNotClass => write!(f, "Superclass must be a class."),
+            RuntimeError::StackOverflow => write!(f, "Stack overflow."),
+            RuntimeError::EmptyList => write!(f, "Can't pop from an empty list."),
+            RuntimeError::InvalidNumber { text } => write!(f, "Can't convert '{text}' to a number."),
+            RuntimeError::Output(error) => write!(f, "Error writing output: {error}"),
+            RuntimeError::Input(error) => write!(f, "Error reading input: {error}"),
+            RuntimeError::AssertionFailed(Some(message)) => write!(f, "{message}"),
+            RuntimeError::AssertionFailed(None) => write!(f, "Assertion failed."),
+            RuntimeError::Other(message) => write!(f, "{message}")
+        }
+    }
+}
+
+impl From<&str> for RuntimeError {
+    fn from(message: &str) -> Self {
+        RuntimeError::Other(message.to_string())
+    }
+}
+
+impl From<String> for RuntimeError {
+    fn from(message: String) -> Self {
+        RuntimeError::Other(message)
+    }
+}
+
+// frames shown in an error's stack trace
+const TRACE_LIMIT: usize = 20;
+
+/// An active call, as listed in a runtime error's stack trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: String,
+    // the line the call was made on
+    pub line: usize,
+    // how many calls in return position this frame was reused for; the
+    // frames they replaced are gone, only their number is kept
+    pub tail_calls: usize
 }
 
 #[derive(Debug)]
-pub struct InterpreterError;
+pub struct InterpreterError {
+    pub line: usize,
+    pub column: Option<usize>,
+    pub kind: RuntimeError,
+    // innermost first
+    pub trace: Vec<Frame>
+}
+
+impl InterpreterError {
+    pub fn new(line: usize, kind: impl Into<RuntimeError>) -> Self {
+        Self {
+            line,
+            column: None,
+            kind: kind.into(),
+            trace: Vec::new()
+        }
+    }
+
+    pub fn at(span: Span, kind: impl Into<RuntimeError>) -> Self {
+        Self {
+            line: span.line,
+            column: Some(span.column),
+            kind: kind.into(),
+            trace: Vec::new()
+        }
+    }
+}
+
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.column {
+            Some(column) => write!(f, "[line {}, col {}] Runtime error: {}", self.line, column, self.kind),
+            None => write!(f, "[line {}] Runtime error: {}", self.line, self.kind)
+        }?;
+        // runs of the same frame, as deep recursion leaves, print once, and
+        // past the innermost few the rest are only counted
+        let mut frames = self.trace.iter().peekable();
+        let mut printed = 0;
+        while let Some(frame) = frames.next() {
+            if printed == TRACE_LIMIT {
+                write!(f, "\n  ... {} more frames", 1 + frames.count())?;
+                break
+            }
+            printed += 1;
+            write!(f, "\n  in {}() at line {}", frame.function, frame.line)?;
+            match frame.tail_calls {
+                0 => (),
+                1 => write!(f, " (after 1 tail call)")?,
+                n => write!(f, " (after {n} tail calls)")?
+            }
+            let mut repeated = 0;
+            while frames.next_if_eq(&frame).is_some() {
+                repeated += 1;
+            }
+            if repeated > 0 {
+                write!(f, "\n  ... repeated {repeated} more times")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<EnvironmentError> for InterpreterError {
+    fn from(error: EnvironmentError) -> Self {
+        match error {
+            EnvironmentError::UndefinedVariable { name, line, column } => InterpreterError::at(Span { line, column }, RuntimeError::UndefinedVariable { name })
+        }
+    }
+}
 
 impl Value {
     pub fn is_truthy(&self) -> bool {
@@ -22,125 +293,287 @@ impl Value {
         }
     }
 
-    pub fn negate(&self) -> Result<Self, InterpreterError> {
+    pub fn negate(&self) -> Result<Self, RuntimeError> {
         match self {
+            Self::Integer(i) => Ok(i.checked_neg().map_or(Self::Number(-(*i as f64)), Self::Integer)),
             Self::Number(n) => Ok(Self::Number(-n)),
-            _ => Err(InterpreterError)
+            _ => Err(RuntimeError::TypeMismatch("Operand must be a number."))
         }
     }
 
-    pub fn not(&self) -> Result<Self, InterpreterError> {
+    pub fn bit_not(&self) -> Result<Self, RuntimeError> {
+        match self {
+            Self::Integer(i) => Ok(Self::Integer(!i)),
+            _ => Err(RuntimeError::TypeMismatch("Operand must be an integer."))
+        }
+    }
+
+    pub fn not(&self) -> Result<Self, RuntimeError> {
         match self.is_truthy() {
             false => Ok(Self::Boolean(true)), 
             true => Ok(Self::Boolean(false))
         }
     }
 
-    pub fn add(&self, other: Value) -> Result<Self, InterpreterError>{
-        match (self, other) {
-            (Self::Number(n1), Self::Number(n2)) => Ok(Self::Number(n1 + n2)),
-            (Self::String(s1), Self::String(s2)) => Ok(Self::String(format!("{s1}{s2}"))),
-            (_, _) => Err(InterpreterError)
+    pub(crate) fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Integer(i) => Some(*i as f64),
+            Self::Number(n) => Some(*n),
+            _ => None
         }
     }
 
-    pub fn subtract(&self, other: Value) -> Result<Self, InterpreterError>{
-        match (self, other) {
-            (Self::Number(n1), Self::Number(n2)) => Ok(Self::Number(n1 - n2)),
-            (_, _) => Err(InterpreterError)
+    // Integers stay integers, promoting to a float when mixed with one or
+    // when the integer result would overflow.
+    fn arithmetic(&self, other: &Value, integer: fn(i64, i64) -> Option<i64>, float: fn(f64, f64) -> f64) -> Option<Self> {
+        if let (Self::Integer(i1), Self::Integer(i2)) = (self, other)
+            && let Some(result) = integer(*i1, *i2) {
+            return Some(Self::Integer(result))
         }
+        Some(Self::Number(float(self.as_float()?, other.as_float()?)))
     }
 
-    pub fn divide(&self, other: Value) -> Result<Self, InterpreterError>{
-        match (self, other) {
-            (Self::Number(n1), Self::Number(n2)) => Ok(Self::Number(n1/n2)),
-            (_, _) => Err(InterpreterError)
+    pub fn add(&self, other: Value) -> Result<Self, RuntimeError>{
+        match (self, &other) {
+            (Self::String(s1), Self::String(s2)) => Ok(Self::String(format!("{s1}{s2}"))),
+            (_, _) => self.arithmetic(&other, i64::checked_add, |n1, n2| n1 + n2)
+                .ok_or(RuntimeError::TypeMismatch("Operands must be two numbers or two strings."))
         }
     }
 
-    pub fn multiply(&self, other: Value) -> Result<Self, InterpreterError>{
-        match (self, other) {
-            (Self::Number(n1), Self::Number(n2)) => Ok(Self::Number(n1 * n2)),
-            (_, _) => Err(InterpreterError)
+    // `add`, but a string operand stringifies a number or boolean on the other side
+    pub fn add_coerced(&self, other: Value) -> Result<Self, RuntimeError> {
+        match (self, &other) {
+            (Self::String(s), Self::Number(_) | Self::Integer(_) | Self::Boolean(_)) => Ok(Self::String(format!("{s}{other}"))),
+            (Self::Number(_) | Self::Integer(_) | Self::Boolean(_), Self::String(s)) => Ok(Self::String(format!("{self}{s}"))),
+            (_, _) => self.add(other)
         }
     }
 
-    pub fn greater(&self, other: Value) -> Result<Self, InterpreterError>{
-        match (self, other) {
-            (Self::Number(n1), Self::Number(n2)) => Ok(Self::Boolean(n1 > &n2)),
-            (_, _) => Err(InterpreterError)
+    pub fn subtract(&self, other: Value) -> Result<Self, RuntimeError>{
+        self.arithmetic(&other, i64::checked_sub, |n1, n2| n1 - n2).ok_or(RuntimeError::TypeMismatch("Operands must be numbers."))
+    }
+
+    // always a float, even for two integers: `5 / 2` is 2.5
+    pub fn divide(&self, other: Value) -> Result<Self, RuntimeError>{
+        match (self.as_float(), other.as_float()) {
+            (Some(_), Some(0.0)) => Err(RuntimeError::DivisionByZero),
+            (Some(n1), Some(n2)) => Ok(Self::Number(n1/n2)),
+            (_, _) => Err(RuntimeError::TypeMismatch("Operands must be numbers."))
         }
     }
 
-    pub fn greater_equal(&self, other: Value) -> Result<Self, InterpreterError>{
-        match (self, other) {
-            (Self::Number(n1), Self::Number(n2)) => Ok(Self::Boolean(n1 >= &n2)),
-            (_, _) => Err(InterpreterError)
+    // truncating division, `7 ~/ 2` is 3
+    pub fn int_divide(&self, other: Value) -> Result<Self, RuntimeError>{
+        match (self.as_float(), other.as_float()) {
+            (Some(_), Some(0.0)) => Err(RuntimeError::DivisionByZero),
+            (Some(_), Some(_)) => self.arithmetic(&other, i64::checked_div, |n1, n2| (n1/n2).trunc()).ok_or(RuntimeError::TypeMismatch("Operands must be numbers.")),
+            (_, _) => Err(RuntimeError::TypeMismatch("Operands must be numbers."))
         }
     }
 
-    pub fn less(&self, other: Value) -> Result<Self, InterpreterError>{
+    pub fn multiply(&self, other: Value) -> Result<Self, RuntimeError>{
+        self.arithmetic(&other, i64::checked_mul, |n1, n2| n1 * n2).ok_or(RuntimeError::TypeMismatch("Operands must be numbers."))
+    }
+
+    // a negative integer exponent gives a float
+    pub fn power(&self, other: Value) -> Result<Self, RuntimeError>{
+        let integer = |base: i64, exponent: i64| base.checked_pow(u32::try_from(exponent).ok()?);
+        self.arithmetic(&other, integer, f64::powf).ok_or(RuntimeError::TypeMismatch("Operands must be numbers."))
+    }
+
+    fn bitwise(&self, other: &Value, op: fn(i64, i64) -> i64) -> Result<Self, RuntimeError> {
         match (self, other) {
-            (Self::Number(n1), Self::Number(n2)) => Ok(Self::Boolean(n1 < &n2)),
-            (_, _) => Err(InterpreterError)
+            (Self::Integer(i1), Self::Integer(i2)) => Ok(Self::Integer(op(*i1, *i2))),
+            (_, _) => Err(RuntimeError::TypeMismatch("Operands must be integers."))
         }
     }
 
-    pub fn less_equal(&self, other: Value) -> Result<Self, InterpreterError>{
+    pub fn bit_and(&self, other: Value) -> Result<Self, RuntimeError> {
+        self.bitwise(&other, |i1, i2| i1 & i2)
+    }
+
+    pub fn bit_or(&self, other: Value) -> Result<Self, RuntimeError> {
+        self.bitwise(&other, |i1, i2| i1 | i2)
+    }
+
+    pub fn bit_xor(&self, other: Value) -> Result<Self, RuntimeError> {
+        self.bitwise(&other, |i1, i2| i1 ^ i2)
+    }
+
+    // `>>` is arithmetic, keeping the sign of a negative value
+    pub fn shift(&self, other: Value, left: bool) -> Result<Self, RuntimeError> {
         match (self, other) {
-            (Self::Number(n1), Self::Number(n2)) => Ok(Self::Boolean(n1 <= &n2)),
-            (_, _) => Err(InterpreterError)
+            (Self::Integer(_), Self::Integer(amount)) if amount < 0 => Err(RuntimeError::InvalidShift("Shift amount must be non-negative.")),
+            (Self::Integer(_), Self::Integer(amount)) if amount >= 64 => Err(RuntimeError::InvalidShift("Shift amount must be less than 64.")),
+            (Self::Integer(i), Self::Integer(amount)) => Ok(Self::Integer(if left { i << amount } else { i >> amount })),
+            (_, _) => Err(RuntimeError::TypeMismatch("Operands must be integers."))
         }
     }
 
-    pub fn is_equal (&self, other: Value) -> bool {
+    // None when a NaN is involved, making every comparison false
+    fn compare(&self, other: &Value) -> Result<Option<Ordering>, RuntimeError> {
         match (self, other) {
-            (Self::Number(n1), Self::Number(n2)) => (*n1) == n2,
-            (Self::Boolean(b1), Self::Boolean(b2)) => (*b1) == b2,
-            (Self::String(s1), Self::String(s2)) => (*s1) == s2,
-            (Self::Null, Self::Null) => true,
-            (_, _) => false
+            (Self::Integer(i1), Self::Integer(i2)) => Ok(Some(i1.cmp(i2))),
+            (Self::String(s1), Self::String(s2)) => Ok(Some(s1.cmp(s2))),
+            (_, _) => match (self.as_float(), other.as_float()) {
+                (Some(n1), Some(n2)) => Ok(n1.partial_cmp(&n2)),
+                (_, _) => Err(RuntimeError::TypeMismatch("Operands must be two numbers or two strings."))
+            }
         }
     }
 
-    pub fn equals (&self, other: Value) -> Result<Self, InterpreterError>{
+    pub fn greater(&self, other: Value) -> Result<Self, RuntimeError>{
+        self.ordered(&other, Ordering::is_gt)
+    }
+
+    pub fn greater_equal(&self, other: Value) -> Result<Self, RuntimeError>{
+        self.ordered(&other, Ordering::is_ge)
+    }
+
+    pub fn less(&self, other: Value) -> Result<Self, RuntimeError>{
+        self.ordered(&other, Ordering::is_lt)
+    }
+
+    pub fn less_equal(&self, other: Value) -> Result<Self, RuntimeError>{
+        self.ordered(&other, Ordering::is_le)
+    }
+
+    fn ordered(&self, other: &Value, test: fn(Ordering) -> bool) -> Result<Self, RuntimeError> {
+        Ok(Self::Boolean(self.compare(other)?.is_some_and(test)))
+    }
+
+    // Lox's `==`
+    pub fn is_equal (&self, other: Value) -> bool {
+        *self == other
+    }
+
+    pub fn is_nan(&self) -> bool {
+        matches!(self, Self::Number(n) if n.is_nan())
+    }
+
+    pub fn equals (&self, other: Value) -> Result<Self, RuntimeError>{
         Ok(Self::Boolean(self.is_equal(other)))
     }
 
-    pub fn not_equals (&self, other: Value) -> Result<Self, InterpreterError>{
+    pub fn not_equals (&self, other: Value) -> Result<Self, RuntimeError>{
         Ok(Self::Boolean(!self.is_equal(other)))
     }
 
-    pub fn stringify(&self) -> String {
+    pub fn type_name(&self) -> &'static str {
         match self {
-            Self::Number(n) => {if n.fract() == 0.0 {(*n as i32).to_string()} else {n.to_string()}},
-            Self::Boolean(b) => b.to_string(), 
-            Self::String(s) => s.clone(),
-            Self::Null => String::from("nil")
+            Self::Integer(_) => "integer",
+            Self::Number(_) => "number",
+            Self::String(_) => "string",
+            Self::Boolean(_) => "bool",
+            Self::Null => "nil",
+            Self::NativeFunction(_) | Self::Function(_) | Self::VmFunction(_) => "function",
+            Self::Class(_) => "class",
+            Self::Instance(_) => "instance",
+            Self::List(_) => "list",
+            Self::Map(_) => "map"
         }
     }
 }
 
+// How a statement finished: fell through normally, or is unwinding out of
+// a `return`, `break` or `continue`. A `return` of a call to a Lox function
+// unwinds as a TailCall instead, for the enclosing call to run in its place.
+enum ControlFlow {
+    Normal(Value),
+    Return(Value),
+    TailCall(LoxFunction, Vec<Value>, usize),
+    Break,
+    Continue
+}
+
 pub struct Interpreter {
-    environment: Environment
+    // root of the scope chain, below the user's globals, so `var clock`
+    // shadows the native rather than replacing it
+    natives: Rc<RefCell<Environment>>,
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    call_stack: Vec<Frame>,
+    out: Rc<RefCell<dyn Write>>,
+    pub coerce_concat: bool,
+    // log each statement and the value it produced to stderr
+    pub trace: bool,
+    // calls deeper than this fail with a stack overflow. Each takes Rust
+    // stack too, around 10 KiB in a debug build and 4 KiB in a release one,
+    // so the thread running the interpreter needs room for this many; tail
+    // calls replace their caller's frame and don't count
+    pub max_call_depth: usize,
+    // if set, calls may not use more than this many bytes of the Rust stack
+    // either, however few of them there are, for a thread with too little
+    // stack for max_call_depth calls
+    pub max_stack_size: Option<usize>,
+    // where the stack was when the outermost call began
+    stack_base: usize
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_writer(Rc::new(RefCell::new(io::stdout())))
+    }
+
+    // the caller keeps its own handle on `out` to read back what was printed
+    pub fn with_writer(out: Rc<RefCell<impl Write + 'static>>) -> Self {
+        Self::with_io(StdinReader::new(), out)
+    }
+
+    pub fn with_io(input: impl BufRead + 'static, out: Rc<RefCell<impl Write + 'static>>) -> Self {
+        let out: Rc<RefCell<dyn Write>> = out;
+        let mut environment = Environment::new();
+        for native in natives::standard(input, Rc::clone(&out)) {
+            environment.define(interner::intern(&native.name), Value::NativeFunction(native));
+        }
+        let natives = Rc::new(RefCell::new(environment));
+        let globals = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(&natives))));
         Self {
-            environment: Environment::new()
+            natives,
+            globals: Rc::clone(&globals),
+            environment: globals,
+            call_stack: Vec::new(),
+            out,
+            coerce_concat: false,
+            trace: false,
+            max_call_depth: 1000,
+            max_stack_size: None,
+            stack_base: 0
         }
     }
 
-    fn binary(&self, expression: BinaryExpression) -> Result<Value, InterpreterError>{
-        let left = self.evaluate(*expression.left)?;
-        let right = self.evaluate(*expression.right)?;
+    pub fn define_native(&mut self, name: &str, arity: impl Into<Arity>, func: impl Fn(Vec<Value>) -> Result<Value, RuntimeError> + 'static) {
+        let native = NativeFunction::new(name, arity, func);
+        self.natives.borrow_mut().define(interner::intern(name), Value::NativeFunction(native));
+    }
 
-        match expression.operator.token_type {
+    fn binary(&mut self, expression: &BinaryExpression) -> Result<Value, InterpreterError>{
+        let left = self.evaluate(&expression.left)?;
+        let right = self.evaluate(&expression.right)?;
+        self.operate(&expression.operator, left, right)
+    }
+
+    fn operate(&self, operator: &Token, left: Value, right: Value) -> Result<Value, InterpreterError> {
+        let result = match operator.token_type {
+            TokenType::PLUS if self.coerce_concat => left.add_coerced(right),
             TokenType::PLUS => left.add(right),
             TokenType::MINUS => left.subtract(right),
             TokenType::SLASH => left.divide(right),
+            TokenType::TILDE_SLASH => left.int_divide(right),
             TokenType::STAR => left.multiply(right),
+            TokenType::STAR_STAR => left.power(right),
+            TokenType::AMPERSAND => left.bit_and(right),
+            TokenType::PIPE => left.bit_or(right),
+            TokenType::CARET => left.bit_xor(right),
+            TokenType::LESS_LESS => left.shift(right, true),
+            TokenType::GREATER_GREATER => left.shift(right, false),
 
             TokenType::GREATER => left.greater(right),
             TokenType::GREATER_EQUAL => left.greater_equal(right),
@@ -148,84 +581,790 @@ impl Interpreter {
             TokenType::LESS_EQUAL => left.less_equal(right),
             TokenType::BANG_EQUAL => left.not_equals(right),
             TokenType::EQUAL_EQUAL => left.equals(right),
-            _ => Err(InterpreterError)
+            TokenType::COMMA => Ok(right),
+            _ => Err(RuntimeError::Other("Unknown binary operator.".to_string()))
+        };
+
+        result.map_err(|message| InterpreterError::at(operator.span(), message))
+    }
+
+    fn logical(&mut self, expression: &LogicalExpression) -> Result<Value, InterpreterError> {
+        let left = self.evaluate(&expression.left)?;
+
+        // short-circuit, yielding the operand itself rather than a boolean
+        match expression.operator.token_type {
+            TokenType::OR if left.is_truthy() => Ok(left),
+            TokenType::AND if !left.is_truthy() => Ok(left),
+            _ => self.evaluate(&expression.right)
         }
     }
 
-    fn grouping(&self, expression: GroupingExpression) -> Result<Value, InterpreterError> {
-        self.evaluate(*expression.expression)
+    fn ternary(&mut self, expression: &TernaryExpression) -> Result<Value, InterpreterError> {
+        if self.evaluate(&expression.condition)?.is_truthy() {
+            self.evaluate(&expression.then_expr)
+        } else {
+            self.evaluate(&expression.else_expr)
+        }
     }
 
-    fn unary(&self, expression: UnaryExpression) -> Result<Value, InterpreterError> {
-        let right = self.evaluate(*expression.right)?;
+    fn call(&mut self, expression: &CallExpression) -> Result<Value, InterpreterError> {
+        let (callee, arguments, line, callee_span) = self.call_operands(expression)?;
+        self.call_value(callee, arguments, line, callee_span)
+    }
 
-        match expression.operator.token_type {
+    // `return f(x);` doesn't call a Lox function from here, it hands it back
+    // for call_function to run in place of the current one, so tail-recursive
+    // functions run in constant stack. Two things follow: unbounded tail
+    // recursion like `fun f() { return f(); }` loops, as `while (true)` does,
+    // rather than overflowing; and a stack trace can't show the frames a tail
+    // call replaced, only how many there were
+    fn tail_call(&mut self, expression: &CallExpression) -> Result<ControlFlow, InterpreterError> {
+        let (callee, arguments, line, callee_span) = self.call_operands(expression)?;
+        match callee {
+            Value::Function(function) => {
+                Self::check_arity(function.arity(), arguments.len(), line)?;
+                Ok(ControlFlow::TailCall(function, arguments, line))
+            },
+            _ => Ok(ControlFlow::Return(self.call_value(callee, arguments, line, callee_span)?))
+        }
+    }
+
+    fn call_operands(&mut self, expression: &CallExpression) -> Result<(Value, Vec<Value>, usize, Span), InterpreterError> {
+        let callee_span = expression.callee.span();
+        let callee = self.evaluate(&expression.callee)?;
+
+        let mut arguments: Vec<Value> = Vec::new();
+        for argument in &expression.arguments {
+            arguments.push(self.evaluate(argument)?);
+        }
+        Ok((callee, arguments, expression.paren.line, callee_span))
+    }
+
+    fn call_value(&mut self, callee: Value, arguments: Vec<Value>, line: usize, callee_span: Span) -> Result<Value, InterpreterError> {
+        match callee {
+            Value::NativeFunction(native) => {
+                Self::check_arity(native.arity, arguments.len(), line)?;
+                native.call(arguments).map_err(|kind| InterpreterError::new(line, kind))
+            },
+            Value::Function(function) => {
+                Self::check_arity(function.arity(), arguments.len(), line)?;
+                self.call_function(function, arguments, line)
+            },
+            Value::Class(class) => self.instantiate(class, arguments, line),
+            _ => Err(InterpreterError::at(callee_span, RuntimeError::NotCallable))
+        }
+    }
+
+    fn instantiate(&mut self, class: Rc<LoxClass>, arguments: Vec<Value>, line: usize) -> Result<Value, InterpreterError> {
+        Self::check_arity(class.arity(), arguments.len(), line)?;
+        let instance = Value::Instance(Rc::new(RefCell::new(LoxInstance::new(Rc::clone(&class)))));
+        if let Some(init) = class.find_method("init") {
+            self.call_function(init.bind(instance.clone()), arguments, line)?;
+        }
+        Ok(instance)
+    }
+
+    pub(crate) fn check_arity(arity: Arity, got: usize, line: usize) -> Result<(), InterpreterError> {
+        if !arity.accepts(got) {
+            return Err(InterpreterError::new(line, RuntimeError::ArityMismatch { expected: arity, got }))
+        }
+        Ok(())
+    }
+
+    fn call_function(&mut self, mut function: LoxFunction, mut arguments: Vec<Value>, line: usize) -> Result<Value, InterpreterError> {
+        let here = stack_address();
+        if self.call_stack.is_empty() {
+            self.stack_base = here;
+        }
+        if self.call_stack.len() >= self.max_call_depth || self.max_stack_size.is_some_and(|max| here.abs_diff(self.stack_base) > max) {
+            return Err(InterpreterError::new(line, RuntimeError::StackOverflow))
+        }
+
+        self.call_stack.push(Frame { function: function.declaration.name.lexeme.clone(), line, tail_calls: 0 });
+        let result = loop {
+            match self.run_function(&function, arguments) {
+                // the tail call takes over this frame
+                Ok(ControlFlow::TailCall(next, next_arguments, next_line)) => {
+                    if let Some(frame) = self.call_stack.last_mut() {
+                        frame.function = next.declaration.name.lexeme.clone();
+                        frame.line = next_line;
+                        frame.tail_calls += 1;
+                    }
+                    function = next;
+                    arguments = next_arguments;
+                },
+                result => break result
+            }
+        };
+        // the innermost frame to see the error records the whole stack
+        let result = result.map_err(|mut error| {
+            if error.trace.is_empty() {
+                error.trace = self.call_stack.iter().rev().cloned().collect();
+            }
+            error
+        });
+        self.call_stack.pop();
+        let result = result?;
+
+        // an initializer always hands back its instance, even on a bare `return;`
+        if function.is_initializer {
+            let this = Token::new(TokenType::THIS, "this".to_string(), None, function.declaration.name.line, function.declaration.name.column);
+            return Ok(function.closure.borrow().get_at(Slot { depth: 0, index: 0 }, &this)?)
+        }
+
+        match result {
+            ControlFlow::Return(val) => Ok(val),
+            ControlFlow::Normal(_) | ControlFlow::TailCall(..) | ControlFlow::Break | ControlFlow::Continue => Ok(Value::Null)
+        }
+    }
+
+    fn run_function(&mut self, function: &LoxFunction, mut arguments: Vec<Value>) -> Result<ControlFlow, InterpreterError> {
+        let params = function.declaration.params.len();
+        let rest = function.declaration.rest.as_ref()
+            .map(|_| arguments.split_off(arguments.len().min(params)));
+
+        let environment = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(&function.closure))));
+        // parameters take the first slots, in order; arity was checked by the caller
+        let passed = arguments.len();
+        for argument in arguments {
+            environment.borrow_mut().define_slot(argument);
+        }
+        self.default_arguments(function, passed, &environment)?;
+        if let Some(rest) = rest {
+            environment.borrow_mut().define_slot(Value::List(Rc::new(RefCell::new(rest))));
+        }
+        self.execute_block(&function.declaration.body, environment)
+    }
+
+    // fills in the optional parameters the call left out, evaluating their
+    // defaults afresh in the call's `environment`, where each can see the
+    // parameters before it
+    fn default_arguments(&mut self, function: &LoxFunction, passed: usize, environment: &Rc<RefCell<Environment>>) -> Result<(), InterpreterError> {
+        let declaration = &function.declaration;
+        // arity was checked by the caller, at least the required ones are here
+        let required = declaration.params.len() - declaration.defaults.len();
+        let missing = &declaration.defaults[passed - required..];
+        if missing.is_empty() {
+            return Ok(())
+        }
+
+        let previous = std::mem::replace(&mut self.environment, Rc::clone(environment));
+        let result = missing.iter().try_for_each(|default| {
+            let value = self.evaluate(default)?;
+            environment.borrow_mut().define_slot(value);
+            Ok(())
+        });
+        self.environment = previous;
+        result
+    }
+
+    fn get(&mut self, expression: &GetExpression) -> Result<Value, InterpreterError> {
+        let name = &expression.name;
+        let object_span = expression.object.span();
+        match self.evaluate(&expression.object)? {
+            Value::Instance(instance) => LoxInstance::get(&instance, &name.lexeme)
+                .ok_or_else(|| InterpreterError::at(name.span(), RuntimeError::UndefinedProperty { name: name.lexeme.clone() })),
+            _ => Err(InterpreterError::at(object_span, RuntimeError::NoProperties))
+        }
+    }
+
+    fn set(&mut self, expression: &SetExpression) -> Result<Value, InterpreterError> {
+        let name = &expression.name;
+        let object_span = expression.object.span();
+        let Value::Instance(instance) = self.evaluate(&expression.object)? else {
+            return Err(InterpreterError::at(object_span, RuntimeError::NoFields))
+        };
+
+        let mut value = self.evaluate(&expression.value)?;
+        if let Some(operator) = &expression.operator {
+            let current = LoxInstance::get(&instance, &name.lexeme)
+                .ok_or_else(|| InterpreterError::at(name.span(), RuntimeError::UndefinedProperty { name: name.lexeme.clone() }))?;
+            value = self.operate(operator, current, value)?;
+        }
+        instance.borrow_mut().set(name.lexeme.clone(), value.clone());
+        Ok(value)
+    }
+
+    fn list(&mut self, expression: &ListExpression) -> Result<Value, InterpreterError> {
+        let mut elements: Vec<Value> = Vec::new();
+        for element in &expression.elements {
+            elements.push(self.evaluate(element)?);
+        }
+        Ok(Value::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn map(&mut self, expression: &MapExpression) -> Result<Value, InterpreterError> {
+        let mut entries: HashMap<String, Value> = HashMap::new();
+        for (key, value) in &expression.entries {
+            let key_span = key.span();
+            let key = Self::map_key(self.evaluate(key)?, key_span)?;
+            entries.insert(key, self.evaluate(value)?);
+        }
+        Ok(Value::Map(Rc::new(RefCell::new(entries))))
+    }
+
+    fn index(&mut self, expression: &IndexExpression) -> Result<Value, InterpreterError> {
+        let (object_span, index_span) = (expression.object.span(), expression.index.span());
+        let object = self.evaluate(&expression.object)?;
+        let index = self.evaluate(&expression.index)?;
+
+        match object {
+            Value::List(list) => {
+                let list = list.borrow();
+                let i = Self::list_index(&index, list.len(), index_span)?;
+                Ok(list[i].clone())
+            },
+            Value::Map(map) => {
+                let key = Self::map_key(index, index_span)?;
+                Ok(map.borrow().get(&key).cloned().unwrap_or(Value::Null))
+            },
+            _ => Err(InterpreterError::at(object_span, RuntimeError::NotIndexable))
+        }
+    }
+
+    fn index_set(&mut self, expression: &IndexSetExpression) -> Result<Value, InterpreterError> {
+        let (object_span, index_span) = (expression.object.span(), expression.index.span());
+        let object = self.evaluate(&expression.object)?;
+        let index = self.evaluate(&expression.index)?;
+        let mut value = self.evaluate(&expression.value)?;
+
+        match object {
+            Value::List(list) => {
+                let i = Self::list_index(&index, list.borrow().len(), index_span)?;
+                if let Some(operator) = &expression.operator {
+                    let current = list.borrow()[i].clone();
+                    value = self.operate(operator, current, value)?;
+                }
+                list.borrow_mut()[i] = value.clone();
+                Ok(value)
+            },
+            Value::Map(map) => {
+                let key = Self::map_key(index, index_span)?;
+                if let Some(operator) = &expression.operator {
+                    let current = map.borrow().get(&key).cloned().unwrap_or(Value::Null);
+                    value = self.operate(operator, current, value)?;
+                }
+                map.borrow_mut().insert(key, value.clone());
+                Ok(value)
+            },
+            _ => Err(InterpreterError::at(object_span, RuntimeError::NotIndexable))
+        }
+    }
+
+    pub(crate) fn list_index(index: &Value, len: usize, span: Span) -> Result<usize, InterpreterError> {
+        match index {
+            Value::Integer(i) if *i >= 0 && (*i as usize) < len => Ok(*i as usize),
+            Value::Integer(_) => Err(InterpreterError::at(span, RuntimeError::IndexOutOfBounds)),
+            Value::Number(n) if n.fract() != 0.0 => Err(InterpreterError::at(span, RuntimeError::TypeMismatch("List index must be an integer."))),
+            Value::Number(n) if *n >= 0.0 && (*n as usize) < len => Ok(*n as usize),
+            Value::Number(_) => Err(InterpreterError::at(span, RuntimeError::IndexOutOfBounds)),
+            _ => Err(InterpreterError::at(span, RuntimeError::TypeMismatch("List index must be a number.")))
+        }
+    }
+
+    pub(crate) fn map_key(key: Value, span: Span) -> Result<String, InterpreterError> {
+        match key {
+            Value::String(s) => Ok(s),
+            _ => Err(InterpreterError::at(span, RuntimeError::TypeMismatch("Map keys must be strings.")))
+        }
+    }
+
+    fn grouping(&mut self, expression: &GroupingExpression) -> Result<Value, InterpreterError> {
+        self.evaluate(&expression.expression)
+    }
+
+    fn unary(&mut self, expression: &UnaryExpression) -> Result<Value, InterpreterError> {
+        let operand_span = expression.right.span();
+        let right = self.evaluate(&expression.right)?;
+
+        let result = match expression.operator.token_type {
             TokenType::MINUS => right.negate(),
             TokenType::BANG => right.not(),
-            _ => Err(InterpreterError {})
-        }
+            TokenType::TILDE => right.bit_not(),
+            _ => Err(RuntimeError::Other("Unknown unary operator.".to_string()))
+        };
+
+        result.map_err(|message| InterpreterError::at(operand_span, message))
     }
 
-    fn literal(&self, expression: LiteralExpression) -> Result<Value, InterpreterError> {
+    fn literal(&self, expression: &LiteralExpression) -> Result<Value, InterpreterError> {
         match expression {
-            LiteralExpression::Boolean(b) => Ok(Value::Boolean(b)),
+            LiteralExpression::Boolean(t) => Ok(Value::Boolean(t.token_type == TokenType::TRUE)),
             LiteralExpression::Null(_) => Ok(Value::Null),
             LiteralExpression::String(t) => {
-                match t.literal {
-                    Some(Literal::STRING(s)) => Ok(Value::String(s)),
-                    _ => Err(InterpreterError {})
+                match &t.literal {
+                    Some(Literal::STRING(s)) => Ok(Value::String(s.clone())),
+                    _ => Err(InterpreterError::at(t.span(), "Invalid string literal."))
                 }
             },
             LiteralExpression::Number(t) => {
                 match t.literal {
                     Some(Literal::NUMBER(n)) => Ok(Value::Number(n)),
-                    _ => Err(InterpreterError {})
+                    Some(Literal::INTEGER(i)) => Ok(Value::Integer(i)),
+                    _ => Err(InterpreterError::at(t.span(), "Invalid number literal."))
                 }
             }
         }
     }
 
-    fn variable(&self, expression: VarExpression) -> Result<Value, InterpreterError> {
-        self.environment.get(expression.name)
+    // globals by name, anything nested into the next slot
+    fn define(&mut self, name: Symbol, value: Value) {
+        if Rc::ptr_eq(&self.environment, &self.globals) {
+            self.globals.borrow_mut().define(name, value);
+        } else {
+            self.environment.borrow_mut().define_slot(value);
+        }
+    }
+
+    fn variable(&mut self, expression: &VarExpression) -> Result<Value, InterpreterError> {
+        match expression.slot {
+            Some(slot) => Ok(self.environment.borrow().get_at(slot, &expression.name)?),
+            None => Ok(self.globals.borrow().get(&expression.name)?)
+        }
     }
 
-    fn statement(&mut self, statement: Statement) -> Result<Value, InterpreterError> {
-        match statement {
-            Statement::Expression(e) => self.evaluate(e),
-            Statement::Print(e) => {
-                let val = self.evaluate(e)?; 
-                println!("{}", val.stringify());
-                Ok(val)
-            },
-            Statement::VarDeclaration(e) => {
-                let mut val = Value::Null;
-                if !e.initializer.is_none() {
-                    val = self.evaluate(e.initializer.unwrap())?;
+    fn this(&mut self, expression: &ThisExpression) -> Result<Value, InterpreterError> {
+        match expression.slot {
+            Some(slot) => Ok(self.environment.borrow().get_at(slot, &expression.keyword)?),
+            None => Ok(self.globals.borrow().get(&expression.keyword)?)
+        }
+    }
+
+    fn super_(&mut self, expression: &SuperExpression) -> Result<Value, InterpreterError> {
+        let keyword = &expression.keyword;
+        let Some(slot) = expression.slot else {
+            return Err(InterpreterError::new(keyword.line, "Can't use 'super' outside of a class."))
+        };
+
+        // `this` lives in the scope just inside the one holding `super`
+        let this = Token::new(TokenType::THIS, "this".to_string(), None, keyword.line, keyword.column);
+        let superclass = self.environment.borrow().get_at(slot, keyword)?;
+        let object = self.environment.borrow().get_at(Slot { depth: slot.depth - 1, index: 0 }, &this)?;
+
+        let method = &expression.method;
+        match superclass {
+            Value::Class(class) => class.find_method(&method.lexeme)
+                .map(|found| Value::Function(found.bind(object)))
+                .ok_or_else(|| InterpreterError::new(method.line, RuntimeError::UndefinedProperty { name: method.lexeme.clone() })),
+            _ => Err(InterpreterError::new(method.line, RuntimeError::SuperclassNotClass))
+        }
+    }
+
+    fn assign(&mut self, expression: &AssignExpression) -> Result<Value, InterpreterError> {
+        let value = self.evaluate(&expression.value)?;
+        match expression.slot {
+            Some(slot) => Ok(self.environment.borrow_mut().assign_at(slot, &expression.name, value)?),
+            None => self.globals.borrow_mut().assign(&expression.name, value).map_err(|error| {
+                let name = &expression.name;
+                if self.natives.borrow().values.contains_key(&name.symbol()) {
+                    InterpreterError::at(name.span(), RuntimeError::AssignToNative { name: name.lexeme.clone() })
+                } else {
+                    error.into()
                 }
+            })
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<ControlFlow, InterpreterError> {
+        if !self.trace {
+            return self.run_statement(statement)
+        }
+
+        let line = statement.span().map_or(0, |span| span.line);
+        eprintln!("[line {line}] {}", statement.print());
+        let result = self.run_statement(statement);
+        if let Ok(ControlFlow::Normal(val) | ControlFlow::Return(val)) = &result {
+            eprintln!("[line {line}] => {val}");
+        }
+        result
+    }
+
+    // each statement has its own method, like expressions in evaluate, so
+    // this frame stays small: it's on the Rust stack once per nested statement
+    fn run_statement(&mut self, statement: &Statement) -> Result<ControlFlow, InterpreterError> {
+        match statement {
+            Statement::Expression(e) => Ok(ControlFlow::Normal(self.evaluate(e)?)),
+            Statement::Print(e) => self.print(e),
+            Statement::VarDeclaration(v) => self.var_declaration(v),
+            Statement::If(s) => self.if_statement(s),
+            Statement::While(s) => self.while_statement(s),
+            Statement::Switch(s) => self.switch_statement(s),
+            Statement::Function(declaration) => self.function_declaration(declaration),
+            Statement::Class(declaration) => self.class_declaration(declaration),
+            Statement::Return(s) => self.return_statement(s),
+            Statement::Break(_) => Ok(ControlFlow::Break),
+            Statement::Continue(_) => Ok(ControlFlow::Continue),
+            Statement::Block(statements) => {
+                let environment = Environment::new_enclosed(Rc::clone(&self.environment));
+                self.execute_block(statements, Rc::new(RefCell::new(environment)))
+            }
+        }
+    }
+
+    fn print(&mut self, expression: &Expression) -> Result<ControlFlow, InterpreterError> {
+        let val = self.evaluate(expression)?;
+        writeln!(self.out.borrow_mut(), "{val}")
+            .map_err(|e| InterpreterError::at(expression.span(), RuntimeError::Output(e.to_string())))?;
+        Ok(ControlFlow::Normal(val))
+    }
+
+    fn var_declaration(&mut self, declaration: &VarDeclaration) -> Result<ControlFlow, InterpreterError> {
+        let mut val = Value::Null;
+        if let Some(initializer) = &declaration.initializer {
+            val = self.evaluate(initializer)?;
+        }
+
+        self.define(declaration.name.symbol(), val.clone());
+        Ok(ControlFlow::Normal(val))
+    }
+
+    fn if_statement(&mut self, s: &IfStatement) -> Result<ControlFlow, InterpreterError> {
+        if self.evaluate(&s.condition)?.is_truthy() {
+            self.statement(&s.then_branch)
+        } else if let Some(else_branch) = &s.else_branch {
+            self.statement(else_branch)
+        } else {
+            Ok(ControlFlow::Normal(Value::Null))
+        }
+    }
+
+    fn while_statement(&mut self, s: &WhileStatement) -> Result<ControlFlow, InterpreterError> {
+        while self.evaluate(&s.condition)?.is_truthy() {
+            match self.statement(&s.body)? {
+                flow @ (ControlFlow::Return(_) | ControlFlow::TailCall(..)) => return Ok(flow),
+                ControlFlow::Break => break,
+                ControlFlow::Normal(_) | ControlFlow::Continue => ()
+            }
+            if let Some(increment) = &s.increment {
+                self.evaluate(increment)?;
+            }
+        }
+        Ok(ControlFlow::Normal(Value::Null))
+    }
+
+    fn switch_statement(&mut self, s: &SwitchStatement) -> Result<ControlFlow, InterpreterError> {
+        let subject = self.evaluate(&s.subject)?;
+        // case values are only evaluated until one matches
+        let mut body = s.default.as_deref();
+        for (value, case_body) in &s.cases {
+            if subject.is_equal(self.evaluate(value)?) {
+                body = Some(case_body);
+                break
+            }
+        }
+        match body {
+            Some(body) => self.execute_block(body, Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(&self.environment))))),
+            None => Ok(ControlFlow::Normal(Value::Null))
+        }
+    }
 
-                self.environment.define(e.name.lexeme.clone(), val.clone());
-                Ok(val.clone())
+    fn function_declaration(&mut self, declaration: &FunctionDeclaration) -> Result<ControlFlow, InterpreterError> {
+        let function = LoxFunction::new(declaration.clone(), Rc::clone(&self.environment), false);
+        self.define(declaration.name.symbol(), Value::Function(function));
+        Ok(ControlFlow::Normal(Value::Null))
+    }
+
+    fn class_declaration(&mut self, declaration: &ClassDeclaration) -> Result<ControlFlow, InterpreterError> {
+        let mut superclass: Option<Rc<LoxClass>> = None;
+        if let Some(expression) = &declaration.superclass {
+            let span = expression.span();
+            match self.evaluate(expression)? {
+                Value::Class(class) => superclass = Some(class),
+                _ => return Err(InterpreterError::at(span, RuntimeError::SuperclassNotClass))
             }
         }
+
+        // methods of a subclass close over a scope holding `super`
+        let mut closure = Rc::clone(&self.environment);
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new_enclosed(closure);
+            environment.define_slot(Value::Class(Rc::clone(superclass)));
+            closure = Rc::new(RefCell::new(environment));
+        }
+
+        let mut methods: HashMap<String, LoxFunction> = HashMap::new();
+        for method in &declaration.methods {
+            let name = method.name.lexeme.clone();
+            let is_initializer = name == "init";
+            methods.insert(name, LoxFunction::new(method.clone(), Rc::clone(&closure), is_initializer));
+        }
+
+        let class = LoxClass::new(declaration.name.lexeme.clone(), superclass, methods);
+        self.define(declaration.name.symbol(), Value::Class(Rc::new(class)));
+        Ok(ControlFlow::Normal(Value::Null))
+    }
+
+    fn return_statement(&mut self, s: &ReturnStatement) -> Result<ControlFlow, InterpreterError> {
+        match &s.value {
+            Some(Expression::Call(call)) => self.tail_call(call),
+            Some(value) => Ok(ControlFlow::Return(self.evaluate(value)?)),
+            None => Ok(ControlFlow::Return(Value::Null))
+        }
+    }
+
+    fn execute_block(&mut self, statements: &[Statement], environment: Rc<RefCell<Environment>>) -> Result<ControlFlow, InterpreterError> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let mut result = Ok(ControlFlow::Normal(Value::Null));
+        for statement in statements {
+            result = self.statement(statement);
+            if !matches!(result, Ok(ControlFlow::Normal(_))) {
+                break
+            }
+        }
+
+        // restore the outer scope even when a statement failed or returned
+        self.environment = previous;
+        match result {
+            Ok(ControlFlow::Normal(_)) => Ok(ControlFlow::Normal(Value::Null)),
+            _ => result
+        }
     }
 
-    pub fn evaluate(&self, expression: Expression) -> Result<Value, InterpreterError> {
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Value, InterpreterError> {
         match expression {
             Expression::Binary(b) => self.binary(b),
             Expression::Grouping(g) => self.grouping(g),
             Expression::Unary(u) => self.unary(u),
             Expression::Literal(l) => self.literal(l),
-            Expression::Variable(v) => self.variable(v)
+            Expression::Variable(v) => self.variable(v),
+            Expression::Assign(a) => self.assign(a),
+            Expression::Logical(l) => self.logical(l),
+            Expression::Ternary(t) => self.ternary(t),
+            Expression::Call(c) => self.call(c),
+            Expression::Get(g) => self.get(g),
+            Expression::Set(s) => self.set(s),
+            Expression::This(t) => self.this(t),
+            Expression::Super(s) => self.super_(s),
+            Expression::List(l) => self.list(l),
+            Expression::Map(m) => self.map(m),
+            Expression::Index(i) => self.index(i),
+            Expression::IndexSet(i) => self.index_set(i)
         }
     }
 
-    pub fn execute(&mut self, statement: Statement) {
-        self.statement(statement).unwrap();
+    pub fn execute(&mut self, statement: &Statement) -> Result<Value, InterpreterError> {
+        match self.statement(statement)? {
+            ControlFlow::Normal(val) | ControlFlow::Return(val) => Ok(val),
+            ControlFlow::TailCall(function, arguments, line) => self.call_function(function, arguments, line),
+            ControlFlow::Break | ControlFlow::Continue => Ok(Value::Null)
+        }
     }
 
-    pub fn interpret(&mut self, statements: Vec<Statement>) {
+    /// Runs `statements` until the first runtime error, which is returned for
+    /// the caller to report.
+    pub fn interpret(&mut self, statements: &[Statement], repl: bool) -> Result<(), InterpreterError> {
         for statement in statements {
-            self.execute(statement);
+            let val = self.execute(statement)?;
+            // In the REPL, echo the value of bare expression statements
+            if repl && let Statement::Expression(expression) = statement {
+                writeln!(self.out.borrow_mut(), "{val}")
+                    .map_err(|e| InterpreterError::at(expression.span(), RuntimeError::Output(e.to_string())))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// an address in the caller's frame, to tell how deep the Rust stack is
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str) -> Result<Vec<Statement>, ()> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().map_err(|_| ())?;
+        let (mut statements, errors) = Parser::new(tokens).parse();
+        let mut resolver = Resolver::new();
+        resolver.resolve(&mut statements);
+        if !errors.is_empty() || !resolver.errors.is_empty() { Err(()) } else { Ok(statements) }
+    }
+
+    // runs `source`, returning the value of its last statement as the REPL
+    // would echo it
+    fn eval(source: &str) -> String {
+        let statements = parse(source).unwrap_or_else(|_| panic!("{source:?} doesn't parse"));
+        let mut interpreter = Interpreter::new();
+        let values = statements.into_iter().map(|statement| match interpreter.execute(&statement) {
+            Ok(value) => value,
+            Err(error) => panic!("{source:?} fails: {}", error.kind)
+        });
+        values.last().expect("source has a statement").to_string()
+    }
+
+    // the first runtime error running `source` raises
+    fn error(source: &str) -> Option<InterpreterError> {
+        let mut interpreter = Interpreter::new();
+        parse(source).unwrap().into_iter().find_map(|statement| interpreter.execute(&statement).err())
+    }
+
+    // whether `name` is undefined once `source` has run
+    fn undefined_after(source: &str, name: &str) -> bool {
+        error(&format!("{source} {name};")).is_some_and(|error| error.kind == RuntimeError::UndefinedVariable { name: name.to_string() })
+    }
+
+    #[test]
+    fn assignment_is_right_associative() {
+        assert_eq!(eval("var a; var b; a = b = 3; a;"), "3");
+        assert_eq!(eval("var a; var b; a = b = 3; b;"), "3");
+    }
+
+    #[test]
+    fn only_variables_can_be_assigned() {
+        assert!(parse("1 = 2;").is_err());
+        assert!(parse("var a; (a) = 2;").is_err());
+    }
+
+    #[test]
+    fn if_takes_the_branch_matching_truthiness() {
+        // only false and nil are falsy
+        let branch = |condition: &str| eval(&format!("var r; if ({condition}) r = \"then\"; else r = \"else\"; r;"));
+        assert_eq!(branch("0"), "then");
+        assert_eq!(branch("\"\""), "then");
+        assert_eq!(branch("nil"), "else");
+        assert_eq!(branch("false"), "else");
+    }
+
+    #[test]
+    fn if_without_else_does_nothing_when_false() {
+        assert_eq!(eval("var r = \"before\"; if (false) r = \"then\"; r;"), "before");
+    }
+
+    #[test]
+    fn while_repeats_until_the_condition_is_falsy() {
+        assert_eq!(eval("var i = 0; while (i < 5) i = i + 1; i;"), "5");
+        assert_eq!(eval("var i = 0; while (false) i = i + 1; i;"), "0");
+    }
+
+    #[test]
+    fn for_desugars_to_a_scoped_while() {
+        assert_eq!(eval("var s = 0; for (var i = 0; i < 4; i = i + 1) s = s + i; s;"), "6");
+        assert_eq!(eval("var i = 0; for (; i < 2;) i = i + 1; i;"), "2");
+        // the initializer's variable doesn't outlive the loop
+        assert!(undefined_after("for (var i = 0; i < 1; i = i + 1) i;", "i"));
+    }
+
+    #[test]
+    fn block_variables_end_with_the_block() {
+        assert_eq!(eval("var a = 1; { var a = 2; a = 3; } a;"), "1");
+        assert_eq!(eval("var a = 1; { a = 2; } a;"), "2");
+        assert!(undefined_after("{ var inner = 1; }", "inner"));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        assert_eq!(eval("var a = 0; false and (a = 1); a;"), "0");
+        assert_eq!(eval("var a = 0; true or (a = 1); a;"), "0");
+        assert_eq!(eval("var a = 0; true and (a = 1); a;"), "1");
+    }
+
+    #[test]
+    fn logical_operators_return_an_operand() {
+        assert_eq!(eval("nil or \"default\";"), "default");
+        assert_eq!(eval("1 and 2;"), "2");
+        assert_eq!(eval("nil and 1;"), "nil");
+    }
+
+    #[test]
+    fn unary_operators_nest() {
+        assert_eq!(eval("!!true;"), "true");
+        assert_eq!(eval("!!nil;"), "false");
+        assert_eq!(eval("--3;"), "3");
+        assert_eq!(eval("!5;"), "false");
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        assert!(error("1 / 0;").is_some());
+        assert!(error("0 / 0;").is_some());
+        assert!(error("var zero = 0; -1 / zero;").is_some());
+        assert_eq!(eval("0 / 1;"), "0");
+    }
+
+    #[test]
+    fn errors_report_their_line() {
+        let error = error("var a = 1;\n\n\"a\" - 1;").expect("subtracting from a string fails");
+        assert_eq!(error.line, 3);
+        assert_eq!(error.kind, RuntimeError::TypeMismatch("Operands must be numbers."));
+    }
+
+    #[test]
+    fn numbers_have_double_precision() {
+        // neither fits in an f32
+        assert_eq!(eval("123456789012345;"), "123456789012345");
+        assert_eq!(eval("16777216 + 1;"), "16777217");
+    }
+
+    #[test]
+    fn exponentiation_is_right_associative() {
+        assert_eq!(eval("2 ** 3 ** 2;"), "512");
+        // and binds tighter than multiplication
+        assert_eq!(eval("2 * 3 ** 2;"), "18");
+        assert_eq!(eval("2 ** -1;"), "0.5");
+    }
+
+    #[test]
+    fn exponentiation_needs_numbers() {
+        for source in ["\"a\" ** 2;", "2 ** nil;"] {
+            assert_eq!(error(source).expect("operand isn't a number").kind, RuntimeError::TypeMismatch("Operands must be numbers."));
+        }
+    }
+
+    #[test]
+    fn ternaries_nest_to_the_right() {
+        let sign = |n| eval(&format!("var n = {n}; n > 0 ? \"positive\" : n < 0 ? \"negative\" : \"zero\";"));
+        assert_eq!(sign(2), "positive");
+        assert_eq!(sign(-2), "negative");
+        assert_eq!(sign(0), "zero");
+    }
+
+    #[test]
+    fn ternary_evaluates_only_the_branch_taken() {
+        assert_eq!(eval("true ? 1 : undefined;"), "1");
+        assert_eq!(eval("false ? undefined : 2;"), "2");
+    }
+
+    #[test]
+    fn comma_operator_yields_its_last_operand() {
+        assert_eq!(eval("1, 2, 3;"), "3");
+    }
+
+    #[test]
+    fn comma_operator_runs_every_operand_in_order() {
+        assert_eq!(eval("var a = 0; var b = (a = 1, a + 1); a;"), "1");
+        assert_eq!(eval("var a = 0; var b = (a = 1, a + 1); b;"), "2");
+    }
+
+    #[test]
+    fn strings_compare_lexicographically() {
+        assert_eq!(eval("\"apple\" < \"banana\";"), "true");
+        assert_eq!(eval("\"b\" <= \"a\";"), "false");
+        assert_eq!(eval("\"ab\" > \"a\";"), "true");
+        assert_eq!(eval("\"a\" >= \"a\";"), "true");
+        // by code point, so upper case sorts first
+        assert_eq!(eval("\"Z\" < \"a\";"), "true");
+    }
+
+    #[test]
+    fn comparing_a_string_with_a_number_is_an_error() {
+        assert_eq!(error("\"a\" < 1;").expect("mixed operands").kind, RuntimeError::TypeMismatch("Operands must be two numbers or two strings."));
+    }
+
+    #[test]
+    fn globals_shadow_natives_without_replacing_them() {
+        let mut interpreter = Interpreter::new();
+        for statement in parse("var clock = \"mine\";").unwrap() {
+            interpreter.execute(&statement).expect("declaration runs");
         }
+        let clock = Token::new(TokenType::IDENTIFIER, "clock".to_string(), None, 1, 1);
+        assert!(matches!(interpreter.globals.borrow().get(&clock), Ok(Value::String(s)) if s == "mine"));
+        assert!(matches!(interpreter.natives.borrow().get(&clock), Ok(Value::NativeFunction(_))));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,141 @@
+pub mod token_type;
+pub mod token;
+pub mod scanner;
+pub mod parser;
+pub mod ast;
+pub mod interpreter;
+pub mod environment;
+pub mod callable;
+pub mod class;
+pub mod resolver;
+pub mod optimizer;
+pub mod interner;
+pub mod vm;
+mod natives;
+
+use std::fmt;
+
+pub use crate::scanner::{ScanError, Scanner};
+pub use crate::parser::{ParseError, Parser};
+pub use crate::resolver::Resolver;
+pub use crate::interpreter::{Frame, Interpreter, InterpreterError, RuntimeError, Value};
+pub use crate::natives::StdinReader;
+
+use crate::token::Token;
+use crate::token_type::TokenType;
+
+#[derive(Debug)]
+pub enum LoxError {
+    Scan(Vec<ScanError>),
+    // resolver errors are included, they're reported the same way
+    Parse(Vec<ParseError>),
+    Runtime(InterpreterError)
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoxError::Scan(errors) => write!(f, "{}", errors.iter().map(ScanError::to_string).collect::<Vec<_>>().join("\n")),
+            LoxError::Parse(errors) => write!(f, "{}", errors.iter().map(ParseError::to_string).collect::<Vec<_>>().join("\n")),
+            LoxError::Runtime(e) => write!(f, "{e}")
+        }
+    }
+}
+
+/// Runs `source` in a fresh interpreter and returns the value of the last
+/// statement. The final expression may omit its semicolon, so `eval("1 + 2")`
+/// yields `Value::Integer(3)`. Nothing is printed on failure; the error
+/// carries the diagnostics.
+///
+/// Recursion deeper than `max_call_depth` (1000 calls) fails with
+/// [`RuntimeError::StackOverflow`]. Calls in return position replace their
+/// caller rather than nesting and don't count, so unbounded tail recursion
+/// such as `fun f() { return f(); } f();` never overflows: like
+/// `while (true) {}`, it runs until the host stops it. Other calls run on
+/// the caller's Rust stack, so a thread with less than about 10 MiB (4 MiB
+/// in a release build) should use [`eval_with`] and lower `max_call_depth`
+/// or set `max_stack_size`.
+pub fn eval(source: &str) -> Result<Value, LoxError> {
+    eval_with(&mut Interpreter::new(), source)
+}
+
+/// Like [`eval`], but runs in `interpreter`, so its settings (such as
+/// `max_call_depth`) apply and its globals carry over between calls.
+pub fn eval_with(interpreter: &mut Interpreter, source: &str) -> Result<Value, LoxError> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens().map_err(LoxError::Scan)?;
+
+    let mut parser = Parser::new(tokens);
+    parser.allow_trailing_expression = true;
+    let (mut statements, errors) = parser.parse();
+    if !errors.is_empty() {
+        return Err(LoxError::Parse(errors))
+    }
+
+    let mut resolver = Resolver::new();
+    resolver.resolve(&mut statements);
+    if !resolver.errors.is_empty() {
+        return Err(LoxError::Parse(resolver.errors))
+    }
+
+    let mut value = Value::Null;
+    for statement in &statements {
+        value = interpreter.execute(statement).map_err(LoxError::Runtime)?;
+    }
+    Ok(value)
+}
+
+pub fn token_error(token: Token, message: &str) {
+    if token.token_type == TokenType::EOF{
+        report(token.line, token.column, " at end".to_string(), message);
+    } else {
+        report(token.line, token.column, format!(" at '{}'", token.lexeme), message);
+    }
+}
+
+/// Prints `error` to stderr with a caret under the source it points at, when
+/// it knows the column, between the message and the stack trace.
+pub fn runtime_error(source: &str, error: &InterpreterError) {
+    let text = error.to_string();
+    let (message, trace) = match text.split_once('\n') {
+        Some((message, trace)) => (message, Some(trace)),
+        None => (text.as_str(), None)
+    };
+    eprintln!("{message}");
+    if let Some(column) = error.column
+        && let Some(caret) = render_caret(source, error.line, column) {
+        eprintln!("{caret}");
+    }
+    if let Some(trace) = trace {
+        eprintln!("{trace}");
+    }
+}
+
+fn report(line: usize, column: usize, loc: String, message: &str) {
+    eprintln!("[line {line}, col {column}] Error{loc}: {message}");
+}
+
+/// Prints `diagnostic` to stderr followed by the source line it points at.
+pub fn report_diagnostic(source: &str, diagnostic: &impl fmt::Display, line: usize, column: usize) {
+    eprintln!("{diagnostic}");
+    if let Some(caret) = render_caret(source, line, column) {
+        eprintln!("{caret}");
+    }
+}
+
+/// Renders line `line` of `source` with a caret under `column`, rustc style:
+///
+/// ```text
+///   3 | print (3;
+///     |         ^
+/// ```
+pub fn render_caret(source: &str, line: usize, column: usize) -> Option<String> {
+    let text = source.lines().nth(line.checked_sub(1)?)?;
+    let gutter = line.to_string();
+
+    // keep tabs so the caret lines up however they're displayed
+    let padding: String = text.chars().take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    Some(format!(" {gutter} | {text}\n {} | {padding}^", " ".repeat(gutter.len())))
+}
@@ -1,93 +1,434 @@
-mod token_type;
-mod token;
-mod scanner;
-mod parser;
-mod ast;
-mod interpreter;
-mod environment;
-
 use std::env;
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
 use std::fs;
+use std::panic;
 use std::process;
+use std::thread;
+
+use rlox::{optimizer, report_diagnostic, runtime_error, Interpreter, Parser, Resolver, Scanner, StdinReader};
+use rlox::token::Token;
+use rlox::vm::{self, Vm};
+
+#[derive(Default)]
+struct Options {
+    warnings: bool,
+    // print the token stream instead of running
+    tokens: bool,
+    // print the parsed tree instead of running
+    ast: bool,
+    // fold constant expressions before running
+    optimize: bool,
+    // compile to bytecode and run that instead of walking the tree
+    vm: bool,
+    // log each executed statement to stderr
+    trace: bool,
+    // `"count: " + 3` concatenates rather than failing
+    coerce_concat: bool
+}
+
+// deep recursion needs far more than the 8 MiB main thread, especially in
+// a debug build; the interpreter is allowed half of it
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
-use crate::scanner::Scanner;
-use crate::token::Token;
-use crate::token_type::TokenType;
-use crate::parser::Parser;
-use crate::interpreter::Interpreter;
+const USAGE: &str = "Usage: jlox [--warnings] [--tokens] [--ast] [--optimize] [--vm] [--trace] [--coerce-concat] [script | -e source]";
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let n_args = args.len();
-
-    if n_args > 2 {
-        panic!("Usage: jlox [script]");
-    } else if n_args == 2 {
-        run_file(&args[1]);
-    } else if n_args == 1{
-        run_prompt();
-    } else {
-        process::exit(0);
+    let main = thread::Builder::new().stack_size(STACK_SIZE).spawn(run_main).expect("Unable to start interpreter thread");
+    // a panic is a bug rather than a Lox error, keep its message and status
+    if let Err(panic) = main.join() {
+        panic::resume_unwind(panic);
     }
 }
 
-fn run_file(path: &String) {
-    let content: String = fs::read_to_string(path).expect("Unable to read file");
-    run(content);
+fn interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.max_stack_size = Some(STACK_SIZE / 2);
+    interpreter
 }
 
-fn run_prompt() {
-    let stdin = io::stdin();
-    let mut handle = stdin.lock();
-    
+fn run_main() {
+    let mut options = Options::default();
+    let mut scripts: Vec<String> = Vec::new();
+    let mut inline: Option<String> = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--warnings" => options.warnings = true,
+            "--tokens" => options.tokens = true,
+            "--ast" => options.ast = true,
+            "--optimize" => options.optimize = true,
+            "--vm" => options.vm = true,
+            "--trace" => options.trace = true,
+            "--coerce-concat" => options.coerce_concat = true,
+            "-e" => match args.next() {
+                Some(source) => inline = Some(source),
+                None => {
+                    eprintln!("Expected source after '-e'.");
+                    eprintln!("{USAGE}");
+                    process::exit(64);
+                }
+            },
+            _ => scripts.push(arg)
+        }
+    }
 
-    loop {
-        print!("> ");
-        let _ = io::stdout().flush();
-        let mut buffer = String::new();
-        handle.read_line(&mut buffer).expect("Error reading input");
-        if buffer == "\n" {
-            break;
+    match (inline, scripts.as_slice()) {
+        (None, []) if io::stdin().is_terminal() => run_prompt(&options),
+        (None, []) => run_stdin(&options),
+        (None, [script]) => run_file(script, &options),
+        (Some(source), []) => run_source(source, &options),
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(64);
         }
-        run(buffer);
     }
 }
 
-fn run(source: String) {
-    let mut scanner: Scanner = Scanner::new(source);
-    let tokens: Vec<Token> = scanner.scan_tokens(); // TODO: Have this return iterator
+fn run_file(path: &String, options: &Options) {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) => {
+            eprintln!("Could not read '{path}': {error}.");
+            process::exit(66);
+        }
+    };
+    run_source(content, options);
+}
+
+// piped input is a whole program rather than REPL lines
+fn run_stdin(options: &Options) {
+    let mut content = String::new();
+    if let Err(error) = io::stdin().read_to_string(&mut content) {
+        eprintln!("Could not read stdin: {error}.");
+        process::exit(74);
+    }
+    run_source(content, options);
+}
 
-    for token in tokens.iter() {
-        println!("{}", token.to_string());
-    } 
+fn run_source(source: String, options: &Options) {
+    let mut interpreter = interpreter();
+    let mut vm = Vm::new();
+    let code = run(source, &mut interpreter, &mut vm, options, false);
+    if code != 0 {
+        process::exit(code);
+    }
+}
 
-    let mut parser: Parser = Parser::new(tokens);
-    let statements_opt = parser.parse();
+fn run_prompt(options: &Options) {
+    let mut interpreter = interpreter();
+    let mut vm = Vm::new();
+    // not io::stdin().lock(): the input() native reads stdin too, and
+    // StdinReader only holds the lock while it reads a line
+    prompt(&mut StdinReader::new(), &mut io::stdout(), &mut interpreter, &mut vm, options);
+}
 
+// the REPL, reading lines from `input` and writing its prompts to `out`;
+// the program's own output goes wherever the interpreter writes
+fn prompt(input: &mut impl BufRead, out: &mut impl Write, interpreter: &mut Interpreter, vm: &mut Vm, options: &Options) {
+    'prompt: loop {
+        // Ctrl-D: end the prompt line before exiting
+        let Some(mut source) = read_line(input, out, "> ") else {
+            let _ = writeln!(out);
+            break
+        };
+        if source == "\n" {
+            break
+        }
+        if let Some(command) = source.trim().strip_prefix(':') {
+            meta_command(command, interpreter, vm, options);
+            continue
+        }
 
-    if parser.had_error {
-        return 
+        // keep reading while the statement is unfinished; an empty line
+        // abandons it
+        while incomplete(&source) {
+            match read_line(input, out, "... ") {
+                None => {
+                    let _ = writeln!(out);
+                    break 'prompt
+                },
+                Some(line) if line == "\n" => continue 'prompt,
+                Some(line) => source.push_str(&line)
+            }
+        }
+        run(source, interpreter, vm, options, true);
     }
-    
-    let statements = statements_opt.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+}
 
-    let mut interpreter = Interpreter::new();
-    interpreter.interpret(statements);
+// `:load path.lox` runs a file into the session, keeping its definitions
+fn meta_command(command: &str, interpreter: &mut Interpreter, vm: &mut Vm, options: &Options) {
+    let (name, argument) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    match (name, argument.trim()) {
+        ("load", "") => eprintln!("Usage: :load <file>"),
+        ("load", path) => match fs::read_to_string(path) {
+            Ok(content) => {
+                run(content, interpreter, vm, options, false);
+            },
+            Err(error) => eprintln!("Could not load '{path}': {error}.")
+        },
+        _ => eprintln!("Unknown command ':{name}'.")
+    }
 }
 
-pub fn error(line: usize, message: &'static str) {
-    report(line, "".to_string(), message);
+// `None` at end of input, or if it can't be read
+fn read_line(input: &mut impl BufRead, out: &mut impl Write, prompt: &str) -> Option<String> {
+    let _ = write!(out, "{prompt}").and_then(|_| out.flush());
+    let mut buffer = String::new();
+    match input.read_line(&mut buffer) {
+        Ok(0) => None,
+        Ok(_) => Some(buffer),
+        Err(error) => {
+            eprintln!("Could not read input: {error}.");
+            None
+        }
+    }
+}
+
+// whether `source` only failed to parse because it stopped partway through;
+// other errors are left for `run` to report
+fn incomplete(source: &str) -> bool {
+    let Ok(tokens) = Scanner::new(source.to_string()).scan_tokens() else {
+        return false
+    };
+    let mut parser = Parser::new(tokens);
+    parser.allow_trailing_expression = true;
+    let (_, errors) = parser.parse();
+    !errors.is_empty() && errors.iter().all(|error| error.at_end())
 }
 
-pub fn token_error(token: Token, message: &'static str) {
-    if token.token_type == TokenType::EOF{
-        report(token.line, " at end".to_string(), message);
+/// Runs `source` and returns the process exit code: 65 for a scan or parse
+/// error, 70 for a runtime error and 0 otherwise.
+fn run(source: String, interpreter: &mut Interpreter, vm: &mut Vm, options: &Options, repl: bool) -> i32 {
+    let mut scanner: Scanner = Scanner::new(source.clone());
+    let tokens: Vec<Token> = match scanner.scan_tokens() {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            for error in &errors {
+                report_diagnostic(&source, error, error.line, error.column);
+            }
+            return 65
+        }
+    };
+
+    if options.tokens {
+        for token in tokens.iter() {
+            println!("{}", token);
+        }
+        return 0
+    }
+
+    let mut parser: Parser = Parser::new(tokens);
+    // `1 + 2` at the prompt, without the semicolon
+    parser.allow_trailing_expression = repl;
+    let (mut statements, errors) = parser.parse();
+
+    for error in &errors {
+        report_diagnostic(&source, error, error.line, error.column);
+    }
+    if !errors.is_empty() {
+        return 65
+    }
+
+    if options.optimize {
+        optimizer::optimize(&mut statements);
+    }
+
+    if options.ast {
+        for statement in statements.iter() {
+            println!("{}", statement.print());
+        }
+        return 0
+    }
+
+    let mut resolver = Resolver::with_source(&source);
+    resolver.warnings = options.warnings;
+    resolver.resolve(&mut statements);
+    for error in &resolver.errors {
+        report_diagnostic(&source, error, error.line, error.column);
+    }
+    if !resolver.errors.is_empty() {
+        return 65
+    }
+
+    let result = if options.vm {
+        let chunk = match vm::compile(&statements, repl) {
+            Ok(chunk) => chunk,
+            Err(error) => {
+                report_diagnostic(&source, &error, error.line, error.column);
+                return 65
+            }
+        };
+        vm.coerce_concat = options.coerce_concat;
+        vm.run(chunk)
     } else {
-        report(token.line, format!(" at '{}'", token.lexeme), message);
+        interpreter.trace = options.trace;
+        interpreter.coerce_concat = options.coerce_concat;
+        interpreter.interpret(&statements, repl)
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            runtime_error(&source, &error);
+            70
+        }
     }
 }
 
-fn report(line: usize, loc: String, message: &'static str) {
-    eprintln!("[line: {line}] Error {loc}: {message}");
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlox::ast::Statement;
+    use std::cell::RefCell;
+    use std::io::{Cursor, Read};
+    use std::rc::Rc;
+
+    // one end of an input shared between the REPL and the interpreter,
+    // handed out a line at a time like StdinReader
+    struct SharedInput {
+        source: Rc<RefCell<Cursor<Vec<u8>>>>,
+        line: Vec<u8>,
+        position: usize
+    }
+
+    impl SharedInput {
+        fn new(source: &Rc<RefCell<Cursor<Vec<u8>>>>) -> Self {
+            Self { source: Rc::clone(source), line: Vec::new(), position: 0 }
+        }
+    }
+
+    impl Read for SharedInput {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let available = self.fill_buf()?;
+            let n = available.len().min(buf.len());
+            buf[..n].copy_from_slice(&available[..n]);
+            self.consume(n);
+            Ok(n)
+        }
+    }
+
+    impl BufRead for SharedInput {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            if self.position >= self.line.len() {
+                self.line.clear();
+                self.position = 0;
+                self.source.borrow_mut().read_until(b'\n', &mut self.line)?;
+            }
+            Ok(&self.line[self.position..])
+        }
+
+        fn consume(&mut self, amount: usize) {
+            self.position += amount;
+        }
+    }
+
+    // runs a REPL session on `input`, returning what the program printed;
+    // the REPL and the input() native read from the same lines
+    fn session(input: &str) -> String {
+        let source = Rc::new(RefCell::new(Cursor::new(input.as_bytes().to_vec())));
+        let printed = Rc::new(RefCell::new(Vec::new()));
+        let mut interpreter = Interpreter::with_io(SharedInput::new(&source), Rc::clone(&printed));
+        let mut vm = Vm::with_writer(Rc::clone(&printed));
+        prompt(&mut SharedInput::new(&source), &mut Vec::new(), &mut interpreter, &mut vm, &Options::default());
+
+        let printed = printed.borrow().clone();
+        String::from_utf8(printed).expect("output is utf-8")
+    }
+
+    // the prompts a REPL session on `input` showed
+    fn prompts(input: &str) -> String {
+        let mut prompts = Vec::new();
+        prompt(&mut input.as_bytes(), &mut prompts, &mut Interpreter::with_writer(Rc::new(RefCell::new(Vec::new()))), &mut Vm::with_writer(Rc::new(RefCell::new(Vec::new()))), &Options::default());
+        String::from_utf8(prompts).expect("prompts are utf-8")
+    }
+
+    // the value of the expression statement `source` in `interpreter`
+    fn value(source: &str, interpreter: &mut Interpreter) -> String {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().expect("source scans");
+        match Parser::new(tokens).parse().0.pop() {
+            Some(Statement::Expression(expression)) => match interpreter.evaluate(&expression) {
+                Ok(value) => value.to_string(),
+                Err(_) => panic!("{source:?} doesn't evaluate")
+            },
+            statement => panic!("{source:?} isn't an expression statement: {statement:?}")
+        }
+    }
+
+    #[test]
+    fn definitions_last_for_the_session() {
+        let mut interpreter = Interpreter::new();
+        let mut vm = Vm::new();
+        run("var a = 1;\n".to_string(), &mut interpreter, &mut vm, &Options::default(), true);
+        run("var b = a + 1;\n".to_string(), &mut interpreter, &mut vm, &Options::default(), true);
+        assert_eq!(value("b;", &mut interpreter), "2");
+    }
+
+    #[test]
+    fn session_echoes_expression_results() {
+        assert_eq!(session("1 + 2;\nvar a = 4;\n\n"), "3\n");
+    }
+
+    #[test]
+    fn session_ends_at_end_of_input() {
+        assert_eq!(prompts("print 1;\n"), "> > \n");
+    }
+
+    #[test]
+    fn unreadable_input_ends_the_session() {
+        let mut prompts = Vec::new();
+        let mut interpreter = Interpreter::with_writer(Rc::new(RefCell::new(Vec::new())));
+        prompt(&mut &b"print 1;\n\xff\n"[..], &mut prompts, &mut interpreter, &mut Vm::new(), &Options::default());
+        assert_eq!(prompts, b"> > \n");
+    }
+
+    #[test]
+    fn a_statement_can_continue_on_the_next_line() {
+        assert_eq!(session("var x =\n  1 + 2;\nprint x;\n\n"), "3\n");
+        assert_eq!(prompts("var x =\n  1 + 2;\nprint x;\n"), "> ... > > \n");
+    }
+
+    #[test]
+    fn an_empty_line_abandons_an_unfinished_statement() {
+        assert_eq!(session("print 1 +\n\nprint 2;\n\n"), "2\n");
+    }
+
+    #[test]
+    fn loaded_functions_are_callable_afterwards() {
+        let path = std::env::temp_dir().join(format!("rlox-{}-load.lox", std::process::id()));
+        fs::write(&path, "fun double(n) { return n * 2; }\n").expect("script is written");
+        let input = format!(":load {}\nprint double(21);\n", path.display());
+        assert_eq!(session(&input), "42\n");
+    }
+
+    #[test]
+    fn loading_a_missing_file_keeps_the_session() {
+        assert_eq!(session(":load does/not/exist.lox\nprint 1;\n"), "1\n");
+    }
+
+    #[test]
+    fn a_bare_expression_prints_its_value() {
+        assert_eq!(session("3 * 4\n"), "12\n");
+        assert_eq!(session("var a = 2;\na + 1\n"), "3\n");
+    }
+
+    #[test]
+    fn echoing_a_list_containing_itself() {
+        assert_eq!(session("var l = [];\npush(l, l);\nl;\n\n"), "nil\n[[...]]\n");
+    }
+
+    #[test]
+    fn session_continues_after_a_syntax_error() {
+        assert_eq!(session("print 1 +;\nprint 2;\n\n"), "2\n");
+    }
+
+    #[test]
+    fn session_continues_after_a_runtime_error() {
+        assert_eq!(session("var a = 1;\nprint nil + 1;\nprint a;\n\n"), "1\n");
+    }
+
+    #[test]
+    fn input_reads_the_line_after_the_statement_calling_it() {
+        let printed = session("var name = input(\"Name? \");\nAda\nprint \"Hi \" + name;\n\n");
+        assert_eq!(printed, "Name? Hi Ada\n");
+    }
+}
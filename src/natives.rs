@@ -0,0 +1,185 @@
+use std::cell::RefCell;
+use std::io::{self, BufRead, Read, Write};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::callable::NativeFunction;
+use crate::interpreter::{RuntimeError, Value};
+
+// The built-in functions, the same for the tree-walker and the VM. `input`
+// writes its prompt to `out` and reads the answer from `input`.
+pub(crate) fn standard(input: impl BufRead + 'static, out: Rc<RefCell<dyn Write>>) -> Vec<NativeFunction> {
+    let input = RefCell::new(input);
+    vec![
+        NativeFunction::new("clock", 0, |_| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            Ok(Value::Number(now.as_secs_f64()))
+        }),
+
+        NativeFunction::new("push", 2, |arguments| {
+            match &arguments[0] {
+                Value::List(list) => {
+                    list.borrow_mut().push(arguments[1].clone());
+                    Ok(Value::Null)
+                },
+                _ => Err(RuntimeError::TypeMismatch("Can only push onto a list."))
+            }
+        }),
+
+        NativeFunction::new("pop", 1, |arguments| {
+            match &arguments[0] {
+                Value::List(list) => list.borrow_mut().pop()
+                    .ok_or(RuntimeError::EmptyList),
+                _ => Err(RuntimeError::TypeMismatch("Can only pop from a list."))
+            }
+        }),
+
+        NativeFunction::new("input", 1, move |arguments| {
+            let mut out = out.borrow_mut();
+            write!(out, "{}", arguments[0]).and_then(|_| out.flush())
+                .map_err(|e| RuntimeError::Output(e.to_string()))?;
+
+            let mut line = String::new();
+            let read = input.borrow_mut().read_line(&mut line)
+                .map_err(|e| RuntimeError::Input(e.to_string()))?;
+            if read == 0 {
+                return Ok(Value::Null)
+            }
+
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Ok(Value::String(line))
+        }),
+
+        NativeFunction::new("is_nan", 1, |arguments| Ok(Value::Boolean(arguments[0].is_nan()))),
+
+        NativeFunction::new("type", 1, |arguments| Ok(Value::String(arguments[0].type_name().to_string()))),
+
+        NativeFunction::new("str", 1, |arguments| Ok(Value::String(arguments[0].to_string()))),
+
+        NativeFunction::new("num", 1, |arguments| {
+            match &arguments[0] {
+                Value::String(s) => s.trim().parse().map(Value::Integer)
+                    .or_else(|_| s.trim().parse().map(Value::Number))
+                    .map_err(|_| RuntimeError::InvalidNumber { text: s.clone() }),
+                _ => Err(RuntimeError::TypeMismatch("Can only convert a string to a number."))
+            }
+        }),
+
+        NativeFunction::new("keys", 1, |arguments| {
+            match &arguments[0] {
+                Value::Map(map) => {
+                    let mut keys = map.borrow().keys().cloned().collect::<Vec<_>>();
+                    keys.sort();
+                    Ok(Value::List(Rc::new(RefCell::new(keys.into_iter().map(Value::String).collect()))))
+                },
+                _ => Err(RuntimeError::TypeMismatch("Can only take the keys of a map."))
+            }
+        }),
+
+        NativeFunction::new("len", 1, |arguments| {
+            match &arguments[0] {
+                Value::List(list) => Ok(Value::Integer(list.borrow().len() as i64)),
+                Value::String(s) => Ok(Value::Integer(s.chars().count() as i64)),
+                _ => Err(RuntimeError::TypeMismatch("Can only take the length of a list or string."))
+            }
+        }),
+
+        // sqrt of a negative number is NaN, as in IEEE 754, rather than an error
+        NativeFunction::new("sqrt", 1, |arguments| {
+            arguments[0].as_float().map(|x| Value::Number(x.sqrt())).ok_or_else(not_a_number)
+        }),
+
+        NativeFunction::new("floor", 1, |arguments| round(&arguments[0], f64::floor)),
+
+        NativeFunction::new("ceil", 1, |arguments| round(&arguments[0], f64::ceil)),
+
+        // halfway cases round away from zero
+        NativeFunction::new("round", 1, |arguments| round(&arguments[0], f64::round)),
+
+        NativeFunction::new("abs", 1, |arguments| {
+            match &arguments[0] {
+                Value::Integer(i) => Ok(i.checked_abs().map_or(Value::Number((*i as f64).abs()), Value::Integer)),
+                Value::Number(n) => Ok(Value::Number(n.abs())),
+                _ => Err(not_a_number())
+            }
+        }),
+
+        // the same as `**`
+        NativeFunction::new("pow", 2, |arguments| {
+            arguments[0].power(arguments[1].clone())
+        }),
+
+        // the message is optional
+        NativeFunction::new("assert", 1..=2, |arguments| {
+            if arguments[0].is_truthy() {
+                return Ok(Value::Null)
+            }
+            match arguments.get(1) {
+                Some(message) => Err(RuntimeError::AssertionFailed(Some(message.to_string()))),
+                None => Err(RuntimeError::AssertionFailed(None))
+            }
+        })
+    ]
+}
+
+/// Reads stdin a line at a time, locking it only for the duration of each
+/// read, so the REPL and the `input()` native can both read from it without
+/// one losing input the other buffered.
+pub struct StdinReader {
+    buffer: Vec<u8>,
+    position: usize
+}
+
+impl StdinReader {
+    pub fn new() -> Self {
+        Self { buffer: Vec::new(), position: 0 }
+    }
+}
+
+impl Default for StdinReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Read for StdinReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for StdinReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position >= self.buffer.len() {
+            self.buffer.clear();
+            self.position = 0;
+            io::stdin().lock().read_until(b'\n', &mut self.buffer)?;
+        }
+        Ok(&self.buffer[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position += amount;
+    }
+}
+
+// floor, ceil and round leave integers as they are, they're already whole
+fn round(value: &Value, round: fn(f64) -> f64) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Integer(i) => Ok(Value::Integer(*i)),
+        Value::Number(n) => Ok(Value::Number(round(*n))),
+        _ => Err(not_a_number())
+    }
+}
+
+fn not_a_number() -> RuntimeError {
+    RuntimeError::TypeMismatch("Argument must be a number.")
+}
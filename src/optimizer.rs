@@ -0,0 +1,215 @@
+use crate::ast::{Expression, FunctionDeclaration, LiteralExpression, Statement};
+use crate::interpreter::Value;
+use crate::token::{Span, Token};
+use crate::token_type::{Literal, TokenType};
+
+// Constant folding. Subexpressions built only from literals are evaluated
+// ahead of time with the same Value operations the interpreter uses; anything
+// that would fail at runtime is left in place so it fails the same way there.
+pub fn optimize(statements: &mut [Statement]) {
+    for statement in statements {
+        self::statement(statement);
+    }
+}
+
+fn statement(statement: &mut Statement) {
+    match statement {
+        Statement::Expression(e) | Statement::Print(e) => expression(e),
+        Statement::VarDeclaration(v) => {
+            if let Some(initializer) = &mut v.initializer {
+                expression(initializer);
+            }
+        },
+        Statement::If(s) => {
+            expression(&mut s.condition);
+            self::statement(&mut s.then_branch);
+            if let Some(else_branch) = &mut s.else_branch {
+                self::statement(else_branch);
+            }
+        },
+        Statement::While(s) => {
+            expression(&mut s.condition);
+            self::statement(&mut s.body);
+            if let Some(increment) = &mut s.increment {
+                expression(increment);
+            }
+        },
+        Statement::Switch(s) => {
+            expression(&mut s.subject);
+            for (value, body) in &mut s.cases {
+                expression(value);
+                optimize(body);
+            }
+            if let Some(default) = &mut s.default {
+                optimize(default);
+            }
+        },
+        Statement::Block(statements) => optimize(statements),
+        Statement::Function(declaration) => function(declaration),
+        Statement::Class(declaration) => {
+            for method in &mut declaration.methods {
+                function(method);
+            }
+        },
+        Statement::Return(s) => {
+            if let Some(value) = &mut s.value {
+                expression(value);
+            }
+        },
+        Statement::Break(_) | Statement::Continue(_) => ()
+    }
+}
+
+fn function(declaration: &mut FunctionDeclaration) {
+    for default in &mut declaration.defaults {
+        expression(default);
+    }
+    optimize(&mut declaration.body);
+}
+
+fn expression(expression: &mut Expression) {
+    // children first, so `1 + 2 * 3` folds bottom-up
+    match expression {
+        Expression::Binary(e) => {
+            self::expression(&mut e.left);
+            self::expression(&mut e.right);
+        },
+        Expression::Logical(e) => {
+            self::expression(&mut e.left);
+            self::expression(&mut e.right);
+        },
+        Expression::Ternary(e) => {
+            self::expression(&mut e.condition);
+            self::expression(&mut e.then_expr);
+            self::expression(&mut e.else_expr);
+        },
+        Expression::Call(e) => {
+            self::expression(&mut e.callee);
+            for argument in &mut e.arguments {
+                self::expression(argument);
+            }
+        },
+        Expression::Assign(a) => self::expression(&mut a.value),
+        Expression::Get(e) => self::expression(&mut e.object),
+        Expression::Set(e) => {
+            self::expression(&mut e.object);
+            self::expression(&mut e.value);
+        },
+        Expression::List(e) => {
+            for element in &mut e.elements {
+                self::expression(element);
+            }
+        },
+        Expression::Map(e) => {
+            for (key, value) in &mut e.entries {
+                self::expression(key);
+                self::expression(value);
+            }
+        },
+        Expression::Index(e) => {
+            self::expression(&mut e.object);
+            self::expression(&mut e.index);
+        },
+        Expression::IndexSet(e) => {
+            self::expression(&mut e.object);
+            self::expression(&mut e.index);
+            self::expression(&mut e.value);
+        },
+        Expression::Grouping(e) => self::expression(&mut e.expression),
+        Expression::Unary(e) => self::expression(&mut e.right),
+        Expression::Variable(_) | Expression::Literal(_) | Expression::This(_) | Expression::Super(_) => ()
+    }
+
+    if let Some(folded) = fold(expression) {
+        *expression = folded;
+    }
+}
+
+fn fold(expression: &Expression) -> Option<Expression> {
+    let span = expression.span();
+    match expression {
+        Expression::Grouping(e) => matches!(*e.expression, Expression::Literal(_)).then(|| (*e.expression).clone()),
+        Expression::Unary(e) => {
+            let right = constant(&e.right)?;
+            let result = match e.operator.token_type {
+                TokenType::MINUS => right.negate(),
+                TokenType::BANG => right.not(),
+                TokenType::TILDE => right.bit_not(),
+                _ => return None
+            };
+            literal(result.ok()?, span)
+        },
+        Expression::Binary(e) => {
+            let (left, right) = (constant(&e.left)?, constant(&e.right)?);
+            // `add` rather than `add_coerced`: a mixed `+` fails here and is
+            // left for the interpreter, which knows whether to coerce
+            let result = match e.operator.token_type {
+                TokenType::PLUS => left.add(right),
+                TokenType::MINUS => left.subtract(right),
+                TokenType::SLASH => left.divide(right),
+                TokenType::TILDE_SLASH => left.int_divide(right),
+                TokenType::STAR => left.multiply(right),
+                TokenType::STAR_STAR => left.power(right),
+                TokenType::AMPERSAND => left.bit_and(right),
+                TokenType::PIPE => left.bit_or(right),
+                TokenType::CARET => left.bit_xor(right),
+                TokenType::LESS_LESS => left.shift(right, true),
+                TokenType::GREATER_GREATER => left.shift(right, false),
+                TokenType::GREATER => left.greater(right),
+                TokenType::GREATER_EQUAL => left.greater_equal(right),
+                TokenType::LESS => left.less(right),
+                TokenType::LESS_EQUAL => left.less_equal(right),
+                TokenType::BANG_EQUAL => left.not_equals(right),
+                TokenType::EQUAL_EQUAL => left.equals(right),
+                _ => return None
+            };
+            literal(result.ok()?, span)
+        },
+        Expression::Logical(e) => {
+            let left = constant(&e.left)?;
+            // the operand that decides the result, as the interpreter short-circuits
+            let decided = match e.operator.token_type {
+                TokenType::OR => left.is_truthy(),
+                TokenType::AND => !left.is_truthy(),
+                _ => return None
+            };
+            Some(if decided { (*e.left).clone() } else { (*e.right).clone() })
+        },
+        _ => None
+    }
+}
+
+fn constant(expression: &Expression) -> Option<Value> {
+    let Expression::Literal(literal) = expression else {
+        return None
+    };
+
+    match literal {
+        LiteralExpression::Boolean(t) => Some(Value::Boolean(t.token_type == TokenType::TRUE)),
+        LiteralExpression::Null(_) => Some(Value::Null),
+        LiteralExpression::String(t) => match &t.literal {
+            Some(Literal::STRING(s)) => Some(Value::String(s.clone())),
+            _ => None
+        },
+        LiteralExpression::Number(t) => match t.literal {
+            Some(Literal::NUMBER(n)) => Some(Value::Number(n)),
+            Some(Literal::INTEGER(i)) => Some(Value::Integer(i)),
+            _ => None
+        }
+    }
+}
+
+// a literal node for `value`, placed where the folded expression began so
+// errors in enclosing expressions still point at the same column
+fn literal(value: Value, span: Span) -> Option<Expression> {
+    let (token_type, lexeme, literal) = match value {
+        Value::Boolean(true) => (TokenType::TRUE, "true".to_string(), None),
+        Value::Boolean(false) => (TokenType::FALSE, "false".to_string(), None),
+        Value::Null => (TokenType::NIL, "nil".to_string(), None),
+        Value::String(s) => (TokenType::STRING, format!("\"{s}\""), Some(Literal::STRING(s))),
+        Value::Integer(i) => (TokenType::NUMBER, i.to_string(), Some(Literal::INTEGER(i))),
+        Value::Number(n) => (TokenType::NUMBER, Value::Number(n).to_string(), Some(Literal::NUMBER(n))),
+        _ => return None
+    };
+    Some(Expression::literal(Token::new(token_type, lexeme, literal, span.line, span.column)))
+}
@@ -1,15 +1,38 @@
+use std::fmt;
 use crate::token::Token;
-use crate::token_error;
 use crate::token_type::TokenType;
 use crate::token_type::TokenType::*;
-use crate::ast::{Expression, Statement, VarDeclaration};
+use crate::ast::{ClassDeclaration, Expression, FunctionDeclaration, IfStatement, ReturnStatement, Statement, SwitchStatement, VarDeclaration, WhileStatement};
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub lexeme: String, // empty at end of input
+    pub message: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let location = if self.lexeme.is_empty() { " at end".to_string() } else { format!(" at '{}'", self.lexeme) };
+        write!(f, "[line {}, col {}] Error{}: {}", self.line, self.column, location, self.message)
+    }
+}
 
-struct ParseError;
+impl ParseError {
+    /// Whether the parser ran out of input, as with `var x =` or an unclosed
+    /// block, rather than hitting a token it didn't expect.
+    pub fn at_end(&self) -> bool {
+        self.lexeme.is_empty()
+    }
+}
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
-    pub had_error: bool
+    loop_depth: usize,
+    errors: Vec<ParseError>,
+    pub allow_trailing_expression: bool
 }
 
 impl Parser {
@@ -17,29 +40,116 @@ impl Parser {
         Self {
             tokens,
             current: 0,
-            had_error: false
+            loop_depth: 0,
+            errors: Vec::new(),
+            allow_trailing_expression: false
         }
     }
 
     fn expression(&mut self) -> Result<Expression, ParseError> {
-        self.equality()
+        self.comma()
+    }
+
+    // argument lists must parse their elements with `assignment` so that
+    // their separating commas aren't swallowed by this rule
+    fn comma(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.assignment()?;
+        while self.match_(&[COMMA]) {
+            let operator = self.previous().clone();
+            let right = self.assignment()?;
+
+            expression = Expression::binary(expression, operator, right);
+        }
+
+        Ok(expression)
+    }
+
+    fn assignment(&mut self) -> Result<Expression, ParseError> {
+        let expression = self.conditional()?;
+
+        if self.match_(&[EQUAL]) {
+            let equals = self.previous().clone();
+            let value = self.assignment()?;
+
+            return match expression {
+                Expression::Variable(v) => Ok(Expression::assign(v.name, value)),
+                Expression::Get(g) => Ok(Expression::set(*g.object, g.name, value, None)),
+                Expression::Index(i) => Ok(Expression::index_set(*i.object, i.bracket, *i.index, value, None)),
+                _ => Err(self.parse_error(equals, "Invalid assignment target."))
+            }
+        }
+
+        if self.match_(&[PLUS_EQUAL, MINUS_EQUAL, STAR_EQUAL, SLASH_EQUAL]) {
+            let equals = self.previous().clone();
+            let operator = Self::compound_operator(&equals);
+            let value = self.assignment()?;
+
+            // variables desugar to `x = x + e`; fields and elements carry the
+            // operator so the object and index are only evaluated once
+            return match expression {
+                Expression::Variable(v) => Ok(Expression::assign(v.name.clone(), Expression::binary(Expression::Variable(v), operator, value))),
+                Expression::Get(g) => Ok(Expression::set(*g.object, g.name, value, Some(operator))),
+                Expression::Index(i) => Ok(Expression::index_set(*i.object, i.bracket, *i.index, value, Some(operator))),
+                _ => Err(self.parse_error(equals, "Invalid assignment target."))
+            }
+        }
+
+        Ok(expression)
+    }
+
+    fn conditional(&mut self) -> Result<Expression, ParseError> {
+        let expression = self.logical_or()?;
+
+        if self.match_(&[QUESTION]) {
+            let then_expr = self.expression()?;
+            self.consume(COLON, "Expect ':' after then branch of conditional expression.")?;
+            let else_expr = self.conditional()?; // right-associative
+
+            return Ok(Expression::ternary(expression, then_expr, else_expr))
+        }
+
+        Ok(expression)
+    }
+
+    fn logical_or(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.logical_and()?;
+        while self.match_(&[OR]) {
+            let operator = self.previous().clone();
+            let right = self.logical_and()?;
+
+            expression = Expression::logical(expression, operator, right);
+        }
+
+        Ok(expression)
+    }
+
+    fn logical_and(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.equality()?;
+        while self.match_(&[AND]) {
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+
+            expression = Expression::logical(expression, operator, right);
+        }
+
+        Ok(expression)
     }
 
     fn equality(&mut self) -> Result<Expression, ParseError>  {
         let mut expression = self.comparison()?;
-        while self.match_(vec![BANG_EQUAL, EQUAL_EQUAL]) {
-            let operator = self.previous();
+        while self.match_(&[BANG_EQUAL, EQUAL_EQUAL]) {
+            let operator = self.previous().clone();
             let right = self.comparison()?;
 
-            expression = Expression::binary(expression.clone(), operator.clone(), right.clone());
+            expression = Expression::binary(expression, operator, right);
         }
 
         Ok(expression)
     }
 
-    fn match_(&mut self, types_: Vec<TokenType>) -> bool {
+    fn match_(&mut self, types_: &[TokenType]) -> bool {
         for t in types_ {
-            if self.check(t) {
+            if self.check(*t) {
                 self.advance();
                 return true
             }
@@ -55,7 +165,7 @@ impl Parser {
         self.peek().token_type == token_type
     }
 
-    fn advance(&mut self) -> Token {
+    fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
             self.current += 1;
         }
@@ -67,21 +177,84 @@ impl Parser {
         self.peek().token_type == EOF
     }
 
-    fn peek(&self) -> Token {
-        self.tokens[self.current].clone()
+    // borrowed, so stepping through tokens doesn't copy their lexemes; callers
+    // clone only the tokens they keep in the AST
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
     }
 
-    fn previous(&self) -> Token {
-        self.tokens[self.current - 1].clone()
+    // `+=` -> `+`, keeping the position so runtime errors point at the operator
+    fn compound_operator(equals: &Token) -> Token {
+        let token_type = match equals.token_type {
+            PLUS_EQUAL => PLUS,
+            MINUS_EQUAL => MINUS,
+            STAR_EQUAL => STAR,
+            _ => SLASH
+        };
+        Token { token_type, lexeme: equals.lexeme.trim_end_matches('=').to_string(), symbol: None, ..equals.clone() }
     }
 
     fn comparison(&mut self) -> Result<Expression, ParseError>  {
+        let mut expression = self.bitwise_or()?;
+        while self.match_(&[LESS, LESS_EQUAL, GREATER, GREATER_EQUAL]) {
+            let operator = self.previous().clone();
+            let right = self.bitwise_or()?;
+
+            expression = Expression::binary(expression, operator, right);
+        }
+
+        Ok(expression)
+    }
+
+    // bitwise operators bind tighter than comparisons, as in Python, so
+    // `x & 1 == 0` tests the masked value
+    fn bitwise_or(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.bitwise_xor()?;
+        while self.match_(&[PIPE]) {
+            let operator = self.previous().clone();
+            let right = self.bitwise_xor()?;
+
+            expression = Expression::binary(expression, operator, right);
+        }
+
+        Ok(expression)
+    }
+
+    fn bitwise_xor(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.bitwise_and()?;
+        while self.match_(&[CARET]) {
+            let operator = self.previous().clone();
+            let right = self.bitwise_and()?;
+
+            expression = Expression::binary(expression, operator, right);
+        }
+
+        Ok(expression)
+    }
+
+    fn bitwise_and(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.shift()?;
+        while self.match_(&[AMPERSAND]) {
+            let operator = self.previous().clone();
+            let right = self.shift()?;
+
+            expression = Expression::binary(expression, operator, right);
+        }
+
+        Ok(expression)
+    }
+
+    fn shift(&mut self) -> Result<Expression, ParseError> {
         let mut expression = self.term()?;
-        while self.match_(vec![LESS, LESS_EQUAL, GREATER, GREATER_EQUAL]) {
-            let operator = self.previous();
+        while self.match_(&[LESS_LESS, GREATER_GREATER]) {
+            let operator = self.previous().clone();
             let right = self.term()?;
 
-            expression = Expression::binary(expression.clone(), operator.clone(), right.clone());
+            expression = Expression::binary(expression, operator, right);
         }
 
         Ok(expression)
@@ -89,64 +262,177 @@ impl Parser {
 
     fn term(&mut self) -> Result<Expression, ParseError> {
         let mut expression = self.factor()?;
-        while self.match_(vec![MINUS, PLUS]) {
-            let operator = self.previous();
+        while self.match_(&[MINUS, PLUS]) {
+            let operator = self.previous().clone();
             let right = self.factor()?;
 
-            expression = Expression::binary(expression.clone(), operator.clone(), right.clone());
+            expression = Expression::binary(expression, operator, right);
         }
 
         Ok(expression)
     }
 
     fn factor(&mut self) -> Result<Expression, ParseError>  {
-        let mut expression = self.unary()?;
-        while self.match_(vec![SLASH, STAR]) {
-            let operator = self.previous();
-            let right = self.unary()?;
+        let mut expression = self.power()?;
+        while self.match_(&[SLASH, STAR, TILDE_SLASH]) {
+            let operator = self.previous().clone();
+            let right = self.power()?;
 
-            expression = Expression::binary(expression.clone(), operator.clone(), right.clone());
+            expression = Expression::binary(expression, operator, right);
+        }
+
+        Ok(expression)
+    }
+
+    fn power(&mut self) -> Result<Expression, ParseError> {
+        let expression = self.unary()?;
+        if self.match_(&[STAR_STAR]) {
+            let operator = self.previous().clone();
+            let right = self.power()?; // right-associative
+
+            return Ok(Expression::binary(expression, operator, right))
         }
 
         Ok(expression)
     }
 
     fn unary(&mut self) -> Result<Expression, ParseError>  {
-        if self.match_(vec![MINUS, BANG]) {
-            let operator = self.previous();
-            let right = self.primary()?;
+        if self.match_(&[MINUS, BANG, TILDE]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+
+            return Ok(Expression::unary(operator, right))
+        }
+
+        self.call()
+    }
 
-            return Ok(Expression::unary(operator.clone(), right.clone()))
+    fn call(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.primary()?;
+
+        loop {
+            if self.match_(&[LEFT_PAREN]) {
+                expression = self.finish_call(expression)?;
+            } else if self.match_(&[DOT]) {
+                let name = self.consume(IDENTIFIER, "Expect property name after '.'.")?;
+                expression = Expression::get(expression, name);
+            } else if self.match_(&[LEFT_BRACKET]) {
+                let bracket = self.previous().clone();
+                let index = self.expression()?;
+                self.consume(RIGHT_BRACKET, "Expect ']' after index.")?;
+                expression = Expression::index(expression, bracket, index);
+            } else {
+                break
+            }
         }
 
-        self.primary()
+        Ok(expression)
+    }
+
+    fn finish_call(&mut self, callee: Expression) -> Result<Expression, ParseError> {
+        let mut arguments: Vec<Expression> = Vec::new();
+        if !self.check(RIGHT_PAREN) {
+            loop {
+                if arguments.len() >= 255 {
+                    self.parse_error(self.peek().clone(), "Can't have more than 255 arguments.");
+                }
+                // `assignment` rather than `expression`, commas separate arguments here
+                arguments.push(self.assignment()?);
+
+                // a trailing comma is allowed, `f(1, 2,)`
+                if !self.match_(&[COMMA]) || self.check(RIGHT_PAREN) {
+                    break
+                }
+            }
+        }
+
+        let paren = self.consume(RIGHT_PAREN, "Expect ')' after arguments.")?;
+        Ok(Expression::call(callee, paren, arguments))
     }
 
     fn primary(&mut self) -> Result<Expression, ParseError> {
-        if self.match_(vec![FALSE, TRUE, NIL, STRING, NUMBER]) {
-            return Ok(Expression::literal(self.previous()))
-        } else if self.match_(vec![LEFT_PAREN]) { // must be parentheses
+        if self.match_(&[FALSE, TRUE, NIL, STRING, NUMBER]) {
+            Ok(Expression::literal(self.previous().clone()))
+        } else if self.match_(&[LEFT_PAREN]) { // must be parentheses
+            let paren = self.previous().clone();
             let expression = self.expression()?;
             self.consume(RIGHT_PAREN, "Expect ')' after expression.")?;
-            return Ok(Expression::grouping(expression)) 
-        } else if self.match_(vec![TokenType::IDENTIFIER]){
-            return Ok(Expression::variable(self.previous()))
+            Ok(Expression::grouping(paren, expression))
+        } else if self.match_(&[LEFT_BRACKET]) {
+            self.list()
+        } else if self.match_(&[LEFT_BRACE]) { // blocks are handled at statement level
+            self.map()
+        } else if self.match_(&[SUPER]) {
+            let keyword = self.previous().clone();
+            self.consume(DOT, "Expect '.' after 'super'.")?;
+            let method = self.consume(IDENTIFIER, "Expect superclass method name.")?;
+            Ok(Expression::super_(keyword, method))
+        } else if self.match_(&[THIS]) {
+            Ok(Expression::this(self.previous().clone()))
+        } else if self.match_(&[TokenType::IDENTIFIER]){
+            Ok(Expression::variable(self.previous().clone()))
         } else {
-            Err(self.parse_error(self.peek(), "Expect expression."))
+            Err(self.parse_error(self.peek().clone(), "Expect expression."))
         }
     }
 
-    fn consume(&mut self, type_: TokenType, message: &'static str) -> Result<Token, ParseError> {
+    fn list(&mut self) -> Result<Expression, ParseError> {
+        let bracket = self.previous().clone();
+        let mut elements: Vec<Expression> = Vec::new();
+        if !self.check(RIGHT_BRACKET) {
+            loop {
+                // `assignment` rather than `expression`, commas separate elements here
+                elements.push(self.assignment()?);
+
+                if !self.match_(&[COMMA]) || self.check(RIGHT_BRACKET) {
+                    break
+                }
+            }
+        }
+
+        self.consume(RIGHT_BRACKET, "Expect ']' after list elements.")?;
+        Ok(Expression::list(bracket, elements))
+    }
+
+    fn map(&mut self) -> Result<Expression, ParseError> {
+        let brace = self.previous().clone();
+        let mut entries: Vec<(Expression, Expression)> = Vec::new();
+        if !self.check(RIGHT_BRACE) {
+            loop {
+                let key = self.assignment()?;
+                self.consume(COLON, "Expect ':' after map key.")?;
+                let value = self.assignment()?;
+                entries.push((key, value));
+
+                if !self.match_(&[COMMA]) || self.check(RIGHT_BRACE) {
+                    break
+                }
+            }
+        }
+
+        self.consume(RIGHT_BRACE, "Expect '}' after map entries.")?;
+        Ok(Expression::map(brace, entries))
+    }
+
+    fn consume(&mut self, type_: TokenType, message: &str) -> Result<Token, ParseError> {
         if self.check(type_) {
-            Ok(self.advance())
+            Ok(self.advance().clone())
         } else {
-            Err(self.parse_error(self.peek(), message))
+            Err(self.parse_error(self.peek().clone(), message))
         }
     }
 
-    fn parse_error(&self, token: Token, message: &'static str) -> ParseError {
-        token_error(token, message);
-        ParseError{}
+    // recorded here as well as returned, since some errors are reported
+    // without unwinding
+    fn parse_error(&mut self, token: Token, message: &str) -> ParseError {
+        let error = ParseError {
+            line: token.line,
+            column: token.column,
+            lexeme: if token.token_type == EOF { String::new() } else { token.lexeme },
+            message: message.to_string()
+        };
+        self.errors.push(error.clone());
+        error
     }
 
     fn synchronize(&mut self) {
@@ -164,6 +450,7 @@ impl Parser {
                 TokenType::FOR => return,
                 TokenType::IF => return,
                 TokenType::WHILE => return,
+                TokenType::SWITCH => return,
                 TokenType::PRINT => return,
                 TokenType::RETURN => return,
                 _ => ()
@@ -175,52 +462,309 @@ impl Parser {
 
     fn print_statement(&mut self) -> Result<Statement, ParseError> {
         let value = self.expression()?;
-        self.consume(TokenType::SEMICOLON, "Expect ';' after value.");
+        self.consume(TokenType::SEMICOLON, "Expect ';' after value.")?;
         Ok(Statement::Print(value))
     }
 
+    fn return_statement(&mut self) -> Result<Statement, ParseError> {
+        let keyword = self.previous().clone();
+        let mut value: Option<Expression> = None;
+        if !self.check(TokenType::SEMICOLON) {
+            value = Some(self.expression()?);
+        }
+
+        self.consume(TokenType::SEMICOLON, "Expect ';' after return value.")?;
+        Ok(Statement::Return(ReturnStatement{keyword, value}))
+    }
+
     fn expression_statement(&mut self) -> Result<Statement, ParseError> {
         let value = self.expression()?;
-        self.consume(TokenType::SEMICOLON, "Expect ';' after value.");
+        if self.allow_trailing_expression && self.is_at_end() {
+            return Ok(Statement::Expression(value))
+        }
+        self.consume(TokenType::SEMICOLON, "Expect ';' after value.")?;
         Ok(Statement::Expression(value))
     }
 
+    fn if_statement(&mut self) -> Result<Statement, ParseError> {
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.statement()?);
+        // eagerly binding `else` here attaches it to the nearest `if`
+        let mut else_branch: Option<Box<Statement>> = None;
+        if self.match_(&[TokenType::ELSE]) {
+            else_branch = Some(Box::new(self.statement()?));
+        }
+
+        Ok(Statement::If(IfStatement{condition, then_branch, else_branch}))
+    }
+
+    fn while_statement(&mut self) -> Result<Statement, ParseError> {
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after condition.")?;
+        let body = Box::new(self.loop_body()?);
+
+        Ok(Statement::While(WhileStatement{condition, body, increment: None}))
+    }
+
+    fn for_statement(&mut self) -> Result<Statement, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.match_(&[TokenType::SEMICOLON]) {
+            None
+        } else if self.match_(&[TokenType::VAR]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let mut condition: Option<Expression> = None;
+        if !self.check(TokenType::SEMICOLON) {
+            condition = Some(self.expression()?);
+        }
+        self.consume(TokenType::SEMICOLON, "Expect ';' after loop condition.")?;
+
+        let mut increment: Option<Expression> = None;
+        if !self.check(TokenType::RIGHT_PAREN) {
+            increment = Some(self.expression()?);
+        }
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after for clauses.")?;
+
+        // desugar into { initializer; while (condition) body } with the
+        // increment kept on the loop so `continue` doesn't skip it
+        let body = self.loop_body()?;
+
+        let condition = condition.unwrap_or_else(|| {
+            Expression::literal(Token::new(TokenType::TRUE, "true".to_string(), None, keyword.line, keyword.column))
+        });
+        let mut body = Statement::While(WhileStatement{condition, body: Box::new(body), increment});
+
+        if let Some(initializer) = initializer {
+            body = Statement::Block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    fn loop_body(&mut self) -> Result<Statement, ParseError> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn break_statement(&mut self) -> Result<Statement, ParseError> {
+        let keyword = self.previous().clone();
+        if self.loop_depth == 0 {
+            self.parse_error(keyword.clone(), "'break' outside loop.");
+        }
+
+        self.consume(TokenType::SEMICOLON, "Expect ';' after 'break'.")?;
+        Ok(Statement::Break(keyword))
+    }
+
+    fn continue_statement(&mut self) -> Result<Statement, ParseError> {
+        let keyword = self.previous().clone();
+        if self.loop_depth == 0 {
+            self.parse_error(keyword.clone(), "'continue' outside loop.");
+        }
+
+        self.consume(TokenType::SEMICOLON, "Expect ';' after 'continue'.")?;
+        Ok(Statement::Continue(keyword))
+    }
+
     fn statement(&mut self) -> Result<Statement, ParseError> {
-        if self.match_(vec![TokenType::PRINT]) {
+        if self.match_(&[TokenType::BREAK]) {
+            return self.break_statement()
+        }
+        if self.match_(&[TokenType::CONTINUE]) {
+            return self.continue_statement()
+        }
+
+        if self.match_(&[TokenType::FOR]) {
+            return self.for_statement()
+        }
+
+        if self.match_(&[TokenType::IF]) {
+            return self.if_statement()
+        }
+
+        if self.match_(&[TokenType::WHILE]) {
+            return self.while_statement()
+        }
+
+        if self.match_(&[TokenType::SWITCH]) {
+            return self.switch_statement()
+        }
+
+        if self.match_(&[TokenType::PRINT]) {
             return self.print_statement()
         }
 
+        if self.match_(&[TokenType::RETURN]) {
+            return self.return_statement()
+        }
+
+        if self.match_(&[TokenType::LEFT_BRACE]) {
+            return Ok(Statement::Block(self.block()?))
+        }
+
         self.expression_statement()
     }
 
+    fn switch_statement(&mut self) -> Result<Statement, ParseError> {
+        self.consume(TokenType::LEFT_PAREN, "Expect '(' after 'switch'.")?;
+        let subject = self.expression()?;
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after switch subject.")?;
+        self.consume(TokenType::LEFT_BRACE, "Expect '{' before switch cases.")?;
+
+        let mut cases: Vec<(Expression, Vec<Statement>)> = Vec::new();
+        while self.match_(&[TokenType::CASE]) {
+            let value = self.expression()?;
+            self.consume(TokenType::COLON, "Expect ':' after case value.")?;
+            cases.push((value, self.case_body()?));
+        }
+
+        let mut default: Option<Vec<Statement>> = None;
+        if self.match_(&[TokenType::DEFAULT]) {
+            self.consume(TokenType::COLON, "Expect ':' after 'default'.")?;
+            default = Some(self.case_body()?);
+            if self.check(TokenType::CASE) || self.check(TokenType::DEFAULT) {
+                return Err(self.parse_error(self.peek().clone(), "Default must be the last case."))
+            }
+        }
+
+        self.consume(TokenType::RIGHT_BRACE, "Expect '}' after switch cases.")?;
+        Ok(Statement::Switch(SwitchStatement{subject, cases, default}))
+    }
+
+    // the statements up to the next case or the end of the switch
+    fn case_body(&mut self) -> Result<Vec<Statement>, ParseError> {
+        let mut statements: Vec<Statement> = Vec::new();
+        while !self.check(TokenType::CASE) && !self.check(TokenType::DEFAULT) && !self.check(TokenType::RIGHT_BRACE) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+        Ok(statements)
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, ParseError> {
+        let mut statements: Vec<Statement> = Vec::new();
+
+        while !self.check(TokenType::RIGHT_BRACE) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+
+        self.consume(TokenType::RIGHT_BRACE, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
     fn var_declaration(&mut self) -> Result<Statement, ParseError> {
         let name = self.consume(TokenType::IDENTIFIER, "Expect variable name.")?;
 
         let mut initializer: Option<Expression> = None;
-        if self.match_(vec![TokenType::EQUAL]) {
+        if self.match_(&[TokenType::EQUAL]) {
             initializer = Some(self.expression()?);
         }
 
-        self.consume(TokenType::SEMICOLON, "Expect ';' after variable declaration.");
-        return Ok(Statement::VarDeclaration(VarDeclaration{name, initializer}))
+        self.consume(TokenType::SEMICOLON, "Expect ';' after variable declaration.")?;
+        Ok(Statement::VarDeclaration(VarDeclaration{name, initializer}))
+    }
+
+    fn function(&mut self, kind: &str) -> Result<FunctionDeclaration, ParseError> {
+        let name = self.consume(TokenType::IDENTIFIER, &format!("Expect {kind} name."))?;
+        self.consume(TokenType::LEFT_PAREN, &format!("Expect '(' after {kind} name."))?;
+
+        let mut params: Vec<Token> = Vec::new();
+        let mut defaults: Vec<Expression> = Vec::new();
+        let mut rest: Option<Token> = None;
+        if !self.check(TokenType::RIGHT_PAREN) {
+            loop {
+                if params.len() >= 255 {
+                    // reported without unwinding, the parser isn't confused
+                    self.parse_error(self.peek().clone(), "Can't have more than 255 parameters.");
+                }
+                if self.match_(&[TokenType::DOT_DOT_DOT]) {
+                    rest = Some(self.consume(TokenType::IDENTIFIER, "Expect parameter name after '...'.")?);
+                    if self.check(TokenType::COMMA) {
+                        return Err(self.parse_error(self.peek().clone(), "Rest parameter must be last."))
+                    }
+                    break
+                }
+                let param = self.consume(TokenType::IDENTIFIER, "Expect parameter name.")?;
+                if self.match_(&[TokenType::EQUAL]) {
+                    defaults.push(self.assignment()?);
+                } else if !defaults.is_empty() {
+                    self.parse_error(param.clone(), "Can't have a required parameter after an optional one.");
+                }
+                params.push(param);
+
+                if !self.match_(&[TokenType::COMMA]) {
+                    break
+                }
+            }
+        }
+        self.consume(TokenType::RIGHT_PAREN, "Expect ')' after parameters.")?;
+
+        self.consume(TokenType::LEFT_BRACE, &format!("Expect '{{' before {kind} body."))?;
+        // loops enclosing the declaration can't be broken out of from its body
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let body = self.block();
+        self.loop_depth = loop_depth;
+        let body = body?;
+
+        Ok(FunctionDeclaration{name, params, defaults, rest, body})
+    }
+
+    fn class_declaration(&mut self) -> Result<Statement, ParseError> {
+        let name = self.consume(TokenType::IDENTIFIER, "Expect class name.")?;
+
+        let mut superclass: Option<Expression> = None;
+        if self.match_(&[TokenType::LESS]) {
+            self.consume(TokenType::IDENTIFIER, "Expect superclass name.")?;
+            superclass = Some(Expression::variable(self.previous().clone()));
+        }
+
+        self.consume(TokenType::LEFT_BRACE, "Expect '{' before class body.")?;
+
+        let mut methods: Vec<FunctionDeclaration> = Vec::new();
+        while !self.check(TokenType::RIGHT_BRACE) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+
+        self.consume(TokenType::RIGHT_BRACE, "Expect '}' after class body.")?;
+        Ok(Statement::Class(ClassDeclaration{name, superclass, methods}))
     }
 
     fn declaration(&mut self) -> Result<Statement, ParseError> {
-        if self.match_(vec![TokenType::VAR]) {
-            return self.var_declaration()
+        if self.match_(&[TokenType::CLASS]) {
+            return self.class_declaration()
+        }
+
+        if self.match_(&[TokenType::FUN]) {
+            return Ok(Statement::Function(self.function("function")?))
+        }
+
+        if self.match_(&[TokenType::VAR]) {
+            self.var_declaration()
         } else {
-            return self.statement()
+            self.statement()
         }
     }
 
-    pub fn parse(&mut self) -> Vec<Option<Statement>> {
-        let mut statements: Vec<Option<Statement>> = Vec::new();
+    /// Parses the whole token stream, returning the statements that parsed
+    /// cleanly along with every error encountered.
+    pub fn parse(&mut self) -> (Vec<Statement>, Vec<ParseError>) {
+        let mut statements: Vec<Statement> = Vec::new();
         while !self.is_at_end() {
             match self.declaration() {
-                Ok(s) => statements.push(Some(s)),
-                Err(_) => {statements.push(None); self.had_error = true; self.synchronize();}
+                Ok(s) => statements.push(s),
+                Err(_) => self.synchronize()
             }
         }
-        statements
+        (statements, std::mem::take(&mut self.errors))
     }
 }
\ No newline at end of file
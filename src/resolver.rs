@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use crate::ast::{Expression, FunctionDeclaration, Slot, Statement};
+use crate::interner::{self, Symbol};
+use crate::token::{Span, Token};
+use crate::parser::ParseError;
+use crate::render_caret;
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass
+}
+
+// Walks the AST before interpretation, recording on each variable and
+// assignment node how many scopes separate it from its declaration and its
+// slot within that scope. Names left unresolved are globals.
+struct Local {
+    defined: bool,
+    // position in its scope, and in the environment that scope becomes
+    index: usize
+}
+
+pub struct Resolver {
+    scopes: Vec<HashMap<Symbol, Local>>,
+    // per scope, the local variables declared but not yet read
+    unread: Vec<HashMap<Symbol, Token>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    source: Option<String>,
+    pub warnings: bool,
+    // reported like parse errors, since they also stop the program running
+    pub errors: Vec<ParseError>
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            unread: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            source: None,
+            warnings: false,
+            errors: Vec::new()
+        }
+    }
+
+    // errors are also shown against the offending source line
+    pub fn with_source(source: &str) -> Self {
+        Self {
+            source: Some(source.to_string()),
+            ..Self::new()
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &mut [Statement]) {
+        let mut terminated = false;
+        for statement in statements {
+            // warn once per run of dead statements
+            if terminated && let Some(span) = statement.span() {
+                self.warning(span, "unreachable code.");
+                terminated = false;
+            }
+            self.statement(statement);
+            if matches!(statement, Statement::Return(_) | Statement::Break(_) | Statement::Continue(_)) {
+                terminated = true;
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Expression(e) | Statement::Print(e) => self.expression(e),
+            Statement::VarDeclaration(v) => {
+                self.declare(&v.name);
+                if let Some(initializer) = &mut v.initializer {
+                    self.expression(initializer);
+                }
+                self.define(&v.name);
+                if let Some(unread) = self.unread.last_mut()
+                    && !v.name.lexeme.starts_with('_') {
+                    unread.insert(v.name.symbol(), v.name.clone());
+                }
+            },
+            Statement::If(s) => {
+                self.expression(&mut s.condition);
+                self.statement(&mut s.then_branch);
+                if let Some(else_branch) = &mut s.else_branch {
+                    self.statement(else_branch);
+                }
+            },
+            Statement::While(s) => {
+                self.expression(&mut s.condition);
+                self.statement(&mut s.body);
+                if let Some(increment) = &mut s.increment {
+                    self.expression(increment);
+                }
+            },
+            Statement::Block(statements) => {
+                self.begin_scope();
+                self.resolve(statements);
+                self.end_scope();
+            },
+            Statement::Switch(s) => {
+                self.expression(&mut s.subject);
+                // each case body is a scope of its own, as if it were a block
+                for (value, body) in &mut s.cases {
+                    self.expression(value);
+                    self.begin_scope();
+                    self.resolve(body);
+                    self.end_scope();
+                }
+                if let Some(default) = &mut s.default {
+                    self.begin_scope();
+                    self.resolve(default);
+                    self.end_scope();
+                }
+            },
+            Statement::Function(declaration) => {
+                // defined eagerly so the function can refer to itself
+                self.declare(&declaration.name);
+                self.define(&declaration.name);
+                self.function(declaration, FunctionType::Function);
+            },
+            Statement::Class(declaration) => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Class;
+
+                self.declare(&declaration.name);
+                self.define(&declaration.name);
+
+                if let Some(superclass) = &mut declaration.superclass {
+                    if let Expression::Variable(v) = superclass
+                        && v.name.lexeme == declaration.name.lexeme {
+                        self.error(&v.name, "A class can't inherit from itself.");
+                    }
+                    self.current_class = ClassType::Subclass;
+                    self.expression(superclass);
+
+                    self.begin_scope();
+                    self.define_synthetic("super");
+                }
+
+                // methods close over a scope holding `this`, mirroring bind()
+                self.begin_scope();
+                self.define_synthetic("this");
+                for method in &mut declaration.methods {
+                    let function_type = if method.name.lexeme == "init" {
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+                    self.function(method, function_type);
+                }
+                self.end_scope();
+
+                if declaration.superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.current_class = enclosing_class;
+            },
+            Statement::Return(s) => {
+                if self.current_function == FunctionType::None {
+                    self.error(&s.keyword, "Can't return from top-level code.");
+                }
+                if let Some(value) = &mut s.value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(&s.keyword, "Can't return a value from an initializer.");
+                    }
+                    self.expression(value);
+                }
+            },
+            Statement::Break(_) | Statement::Continue(_) => ()
+        }
+    }
+
+    fn function(&mut self, declaration: &mut FunctionDeclaration, function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+
+        self.begin_scope();
+        // defaults are evaluated in the call's scope, where the parameters
+        // before each are already bound
+        let required = declaration.params.len() - declaration.defaults.len();
+        for (index, param) in declaration.params.iter().enumerate() {
+            if let Some(default) = index.checked_sub(required).map(|optional| &mut declaration.defaults[optional]) {
+                self.expression(default);
+            }
+            self.declare(param);
+            self.define(param);
+        }
+        if let Some(rest) = &declaration.rest {
+            self.declare(rest);
+            self.define(rest);
+        }
+        self.resolve(&mut declaration.body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+    }
+
+    fn expression(&mut self, expression: &mut Expression) {
+        match expression {
+            Expression::Variable(v) => {
+                if let Some(false) = self.scopes.last().and_then(|scope| scope.get(&v.name.symbol())).map(|local| local.defined) {
+                    self.error(&v.name, "Can't read local variable in its own initializer.");
+                }
+                v.slot = self.resolve_local(&v.name);
+                if let Some(slot) = v.slot {
+                    let scope = self.unread.len() - 1 - slot.depth;
+                    self.unread[scope].remove(&v.name.symbol());
+                }
+            },
+            Expression::Assign(a) => {
+                self.expression(&mut a.value);
+                a.slot = self.resolve_local(&a.name);
+            },
+            Expression::Binary(e) => {
+                self.expression(&mut e.left);
+                self.expression(&mut e.right);
+            },
+            Expression::Logical(e) => {
+                self.expression(&mut e.left);
+                self.expression(&mut e.right);
+            },
+            Expression::Ternary(e) => {
+                self.expression(&mut e.condition);
+                self.expression(&mut e.then_expr);
+                self.expression(&mut e.else_expr);
+            },
+            Expression::Call(e) => {
+                self.expression(&mut e.callee);
+                for argument in &mut e.arguments {
+                    self.expression(argument);
+                }
+            },
+            Expression::This(e) => {
+                if self.current_class == ClassType::None {
+                    self.error(&e.keyword, "Can't use 'this' outside of a class.");
+                    return
+                }
+                e.slot = self.resolve_local(&e.keyword);
+            },
+            Expression::Super(e) => {
+                match self.current_class {
+                    ClassType::None => self.error(&e.keyword, "Can't use 'super' outside of a class."),
+                    ClassType::Class => self.error(&e.keyword, "Can't use 'super' in a class with no superclass."),
+                    ClassType::Subclass => ()
+                }
+                e.slot = self.resolve_local(&e.keyword);
+            },
+            Expression::Get(e) => self.expression(&mut e.object),
+            Expression::Set(e) => {
+                self.expression(&mut e.value);
+                self.expression(&mut e.object);
+            },
+            Expression::List(e) => {
+                for element in &mut e.elements {
+                    self.expression(element);
+                }
+            },
+            Expression::Map(e) => {
+                for (key, value) in &mut e.entries {
+                    self.expression(key);
+                    self.expression(value);
+                }
+            },
+            Expression::Index(e) => {
+                self.expression(&mut e.object);
+                self.expression(&mut e.index);
+            },
+            Expression::IndexSet(e) => {
+                self.expression(&mut e.value);
+                self.expression(&mut e.object);
+                self.expression(&mut e.index);
+            },
+            Expression::Grouping(e) => self.expression(&mut e.expression),
+            Expression::Unary(e) => self.expression(&mut e.right),
+            Expression::Literal(_) => ()
+        }
+    }
+
+    fn resolve_local(&self, name: &Token) -> Option<Slot> {
+        self.scopes.iter().rev().enumerate().find_map(|(depth, scope)| {
+            scope.get(&name.symbol()).map(|local| Slot { depth, index: local.index })
+        })
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.unread.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+        let Some(unread) = self.unread.pop() else {
+            return
+        };
+
+        let mut unread = unread.into_values().collect::<Vec<_>>();
+        unread.sort_by_key(|name| (name.line, name.column));
+        for name in unread {
+            self.warning(name.span(), &format!("unused variable '{}'.", name.lexeme));
+        }
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return
+        };
+
+        if scope.contains_key(&name.symbol()) {
+            self.error(name, "Already a variable with this name in this scope.");
+            return
+        }
+        let index = scope.len();
+        scope.insert(name.symbol(), Local { defined: false, index });
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(local) = self.scopes.last_mut().and_then(|scope| scope.get_mut(&name.symbol())) {
+            local.defined = true;
+        }
+    }
+
+    // `this` and `super`, which the interpreter binds into a scope of their own
+    fn define_synthetic(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let index = scope.len();
+            scope.insert(interner::intern(name), Local { defined: true, index });
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(ParseError {
+            line: token.line,
+            column: token.column,
+            lexeme: token.lexeme.clone(),
+            message: message.to_string()
+        });
+    }
+
+    // only with `--warnings`, they don't stop the program running
+    fn warning(&self, span: Span, message: &str) {
+        if !self.warnings {
+            return
+        }
+        eprintln!("[line {}, col {}] Warning: {}", span.line, span.column, message);
+        if let Some(caret) = self.source.as_deref().and_then(|source| render_caret(source, span.line, span.column)) {
+            eprintln!("{caret}");
+        }
+    }
+}
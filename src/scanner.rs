@@ -1,30 +1,54 @@
 use crate::token::Token;
 use crate::token_type::TokenType::{self, *};
 use crate::token_type::Literal;
-use crate::error;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub line: usize,
+    pub column: usize,
+    pub message: &'static str
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[line {}, col {}] Error: {}", self.line, self.column, self.message)
+    }
+}
 
 pub struct Scanner {
-    source: String, 
-    tokens: Vec<Token>,
+    source: Vec<char>, 
+    // produced by the last scan_token() call, not yet handed out
+    pending: VecDeque<Result<Token, ScanError>>,
+    reached_eof: bool,
     start: usize, 
     current: usize, 
     line: usize,
+    line_start: usize,
+    start_column: usize,
     keywords: HashMap<&'static str, TokenType>
 }
 
 impl Scanner {
     pub fn new(source: String) -> Self{
         Self {
-            source, 
-            tokens: Vec::new(), 
+            source: source.chars().collect(), 
+            pending: VecDeque::new(),
+            reached_eof: false,
             start: 0, 
             current: 0, 
             line: 1,
+            line_start: 0,
+            start_column: 1,
             keywords: HashMap::from([
                 ("and", AND),
+                ("break", BREAK),
+                ("case", CASE),
                 ("class", CLASS),
+                ("continue", CONTINUE),
+                ("default", DEFAULT),
                 ("else", ELSE),
                 ("false", FALSE),
                 ("for", FOR), 
@@ -35,6 +59,7 @@ impl Scanner {
                 ("print", PRINT),
                 ("return", RETURN),
                 ("super", SUPER),
+                ("switch", SWITCH),
                 ("this", THIS),
                 ("true", TRUE), 
                 ("var", VAR),
@@ -43,19 +68,33 @@ impl Scanner {
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Vec<Token> {
-        while !self.is_at_end() {
-            self.start = self.current; 
-            self.scan_token();
+    /// Scans the whole source, returning every lexical error if there were
+    /// any. Scanning carries on past an error so they're all reported at once.
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<ScanError>> {
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut errors: Vec<ScanError> = Vec::new();
+        for result in self.by_ref() {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(error) => errors.push(error)
+            }
         }
 
-        self.tokens.push(Token::new(EOF, "".to_string(),None, self.line));
-        return self.tokens.clone();
+        if errors.is_empty() { Ok(tokens) } else { Err(errors) }
+    }
+
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
 
+    // 1-based column of the next unconsumed character
+    fn column(&self) -> usize {
+        self.current - self.line_start + 1
     }
 
     fn is_at_end(&self) -> bool {
-        return self.current >= self.source.len();
+        self.current >= self.source.len()
     }
 
     fn scan_token(&mut self) {
@@ -65,40 +104,68 @@ impl Scanner {
             ')' => self.add_token(RIGHT_PAREN),
             '{' => self.add_token(LEFT_BRACE),
             '}' => self.add_token(RIGHT_BRACE),
+            '[' => self.add_token(LEFT_BRACKET),
+            ']' => self.add_token(RIGHT_BRACKET),
             ',' => self.add_token(COMMA),
-            '.' => self.add_token(DOT),
-            '-' => self.add_token(MINUS),
-            '+' => self.add_token(PLUS),
+            '.' => {
+                let token = if self.peek() == '.' && self.peek_next() == '.' {
+                    self.advance();
+                    self.advance();
+                    DOT_DOT_DOT
+                } else {
+                    DOT
+                };
+                self.add_token(token)
+            },
+            '-' => {let found = self.find('='); self.add_token(if found {MINUS_EQUAL} else {MINUS})},
+            '+' => {let found = self.find('='); self.add_token(if found {PLUS_EQUAL} else {PLUS})},
             ';' => self.add_token(SEMICOLON),
-            '*' => self.add_token(STAR),
+            '?' => self.add_token(QUESTION),
+            ':' => self.add_token(COLON),
+            '&' => self.add_token(AMPERSAND),
+            '|' => self.add_token(PIPE),
+            '^' => self.add_token(CARET),
+            '~' => {let found = self.find('/'); self.add_token(if found {TILDE_SLASH} else {TILDE})},
+            '*' => {
+                let token = if self.find('*') {STAR_STAR} else if self.find('=') {STAR_EQUAL} else {STAR};
+                self.add_token(token)
+            },
             '!' => {let found = self.find('='); self.add_token(if found {BANG_EQUAL} else {BANG})},
             '=' => {let found = self.find('='); self.add_token(if found {EQUAL_EQUAL} else {EQUAL})},
-            '>' => {let found = self.find('='); self.add_token(if found {GREATER_EQUAL} else {GREATER})},
-            '<' => {let found = self.find('='); self.add_token(if found {LESS_EQUAL} else {LESS})},
+            '>' => {
+                let token = if self.find('=') {GREATER_EQUAL} else if self.find('>') {GREATER_GREATER} else {GREATER};
+                self.add_token(token)
+            },
+            '<' => {
+                let token = if self.find('=') {LESS_EQUAL} else if self.find('<') {LESS_LESS} else {LESS};
+                self.add_token(token)
+            },
             '/' => {
                 if self.find('/') {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                } else if self.find('*') {
+                    self.block_comment();
+                } else if self.find('=') {
+                    self.add_token(SLASH_EQUAL);
                 } else {
                     self.add_token(SLASH);
                 }
             },
-            ' ' => return,
-            '\r' => return,
-            '\t' => return, 
-            '\n' => { self.line += 1; return},
+            ' ' | '\r' | '\t' => (),
+            '\n' => self.newline(),
             '"' => self.string(),
 
 
             _ => {
-                if self.is_digit(c) { self.number() } else if self.is_alpha(c) {self.identifier();} else {error(self.line, "Unexpected character.")}
+                if c == '0' && self.find('x') { self.hex_number() } else if self.is_digit(c) { self.number() } else if self.is_alpha(c) {self.identifier();} else {self.error("Unexpected character.")}
             }
         }
     }
 
     fn advance(&mut self) -> char {
-        let c = self.source.chars().nth(self.current).unwrap();
+        let c = self.source[self.current];
         self.current += 1;
         c
     }
@@ -108,12 +175,16 @@ impl Scanner {
     }
 
     fn add_literal(&mut self, token: TokenType, literal: Option<Literal>) {
-        let text = self.source[self.start..self.current].to_string();
-        self.tokens.push(Token::new(token, text, literal, self.line))
+        let text = self.text(self.start, self.current);
+        self.pending.push_back(Ok(Token::new(token, text, literal, self.line, self.start_column)))
+    }
+
+    fn text(&self, start: usize, end: usize) -> String {
+        self.source[start..end].iter().collect()
     }
 
     fn find(&mut self, expected: char) -> bool {
-        if self.is_at_end() || self.source.chars().nth(self.current) != Some(expected) {
+        if self.is_at_end() || self.source[self.current] != expected {
             false
         } else {
             self.current += 1;
@@ -125,57 +196,163 @@ impl Scanner {
         if self.is_at_end() {
             '\0'
         } else {
-            self.source.chars().nth(self.current).unwrap()
+            self.source[self.current]
+        }
+    }
+
+    fn block_comment(&mut self) {
+        // reported from the line it opened on
+        let start_line = self.line;
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                self.error_at(start_line, "Unterminated block comment.");
+                return;
+            }
+
+            if self.peek() == '/' && self.peek_next() == '*' {
+                depth += 1;
+                self.advance();
+                self.advance();
+            } else if self.peek() == '*' && self.peek_next() == '/' {
+                depth -= 1;
+                self.advance();
+                self.advance();
+            } else if self.advance() == '\n' {
+                self.newline();
+            }
         }
     }
 
     fn string(&mut self) {
+        // reported from the opening quote, not wherever the input ran out
+        let start_line = self.line;
+        let mut value = String::new();
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            let c = self.advance();
+            match c {
+                '\n' => {
+                    self.newline();
+                    value.push(c);
+                },
+                '\\' if !self.is_at_end() => match self.advance() {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'r' => value.push('\r'),
+                    '"' => value.push('"'),
+                    '\\' => value.push('\\'),
+                    '0' => value.push('\0'),
+                    e => {
+                        if e == '\n' {
+                            self.newline();
+                        }
+                        self.error("Invalid escape sequence.");
+                    }
+                },
+                _ => value.push(c)
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            error(self.line, "Unterminated string");
+            self.error_at(start_line, "Unterminated string starting here.");
             return;
         }
 
         self.advance(); // closing "
 
-
-        let text = &self.source[self.start + 1..self.current - 1];
-        self.add_literal(STRING, Some(Literal::STRING(text.to_string())))
+        self.add_literal(STRING, Some(Literal::STRING(value)))
     }
 
     fn is_digit(&self, c: char) -> bool {
-        return c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
     fn number(&mut self) {
-        while self.is_digit(self.peek()) {
-            self.advance();
+        if !self.digits() {
+            return;
         }
 
+        let mut is_float = false;
         if self.peek() == '.' && self.is_digit(self.peek_next()) {
+            is_float = true;
             self.advance();
+            if !self.digits() {
+                return;
+            }
         }
 
+        if self.peek() == 'e' || self.peek() == 'E' {
+            is_float = true;
+            self.advance();
+            if self.peek() == '+' || self.peek() == '-' {
+                self.advance();
+            }
+
+            if !self.is_digit(self.peek()) {
+                self.error("Expect digits in exponent.");
+                return;
+            }
 
-        while self.is_digit(self.peek()) {
+            if !self.digits() {
+                return;
+            }
+        }
+
+        let text = self.text(self.start, self.current).replace('_', "");
+        if is_float {
+            match text.parse() {
+                Ok(n) => self.add_literal(NUMBER, Some(Literal::NUMBER(n))),
+                Err(_) => self.error("Invalid number literal.")
+            }
+        } else {
+            // too big for an integer, it's still a number
+            match text.parse() {
+                Ok(i) => self.add_literal(NUMBER, Some(Literal::INTEGER(i))),
+                Err(_) => match text.parse() {
+                    Ok(n) => self.add_literal(NUMBER, Some(Literal::NUMBER(n))),
+                    Err(_) => self.error("Invalid number literal.")
+                }
+            }
+        }
+    }
+
+    /// Consumes a run of digits, allowing single underscores between them.
+    fn digits(&mut self) -> bool {
+        while self.is_digit(self.peek()) || self.peek() == '_' {
+            if self.peek() == '_' && !self.is_digit(self.peek_next()) {
+                self.error("Underscore must separate digits.");
+                while self.is_alphanumeric(self.peek()) {
+                    self.advance();
+                }
+                return false;
+            }
+            self.advance();
+        }
+        true
+    }
+
+    fn hex_number(&mut self) {
+        while self.peek().is_ascii_hexdigit() {
             self.advance();
         }
 
-        let text = &self.source[self.start..self.current];
-        self.add_literal(NUMBER, Some(Literal::NUMBER(text.parse().unwrap())));
+        let digits = self.text(self.start + 2, self.current);
+        if digits.is_empty() {
+            self.error("Expect hex digits after '0x'.");
+            return;
+        }
+
+        match i64::from_str_radix(&digits, 16) {
+            Ok(i) => self.add_literal(NUMBER, Some(Literal::INTEGER(i))),
+            Err(_) => self.error("Hex literal is too large.")
+        }
     }
 
     fn peek_next(&self) -> char {
         if self.current + 1 >= self.source.len() {
             '\0'
         } else {
-            self.source.chars().nth(self.current + 1).unwrap()
+            self.source[self.current + 1]
         }
     }
 
@@ -184,16 +361,292 @@ impl Scanner {
             self.advance();
         }
 
-        let text = &self.source[self.start..self.current];
-        let typ = match self.keywords.get(&text) { None => IDENTIFIER, Some(t) => t.clone()};
+        let text = self.text(self.start, self.current);
+        let typ = match self.keywords.get(text.as_str()) { None => IDENTIFIER, Some(t) => *t};
         self.add_token(typ)
     }
 
+    fn error(&mut self, message: &'static str) {
+        self.error_at(self.line, message);
+    }
+
+    fn error_at(&mut self, line: usize, message: &'static str) {
+        self.pending.push_back(Err(ScanError { line, column: self.start_column, message }));
+    }
+
     fn is_alpha(&self, c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+        c.is_ascii_alphabetic() || c == '_'
     }
 
     fn is_alphanumeric(&self, c: char) -> bool {
         self.is_alpha(c) || self.is_digit(c)
     }
-}
\ No newline at end of file
+}
+
+// Scans lazily, a token (or error) at a time, finishing with EOF.
+impl Iterator for Scanner {
+    type Item = Result<Token, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.pending.pop_front() {
+                return Some(result)
+            }
+
+            if self.is_at_end() {
+                if self.reached_eof {
+                    return None
+                }
+                self.reached_eof = true;
+                return Some(Ok(Token::new(EOF, "".to_string(), None, self.line, self.column())))
+            }
+
+            self.start = self.current;
+            self.start_column = self.column();
+            self.scan_token();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        Scanner::new(source.to_string()).scan_tokens()
+            .unwrap_or_else(|errors| panic!("{source:?} doesn't scan: {errors:?}"))
+    }
+
+    fn errors(source: &str) -> Vec<ScanError> {
+        match Scanner::new(source.to_string()).scan_tokens() {
+            Ok(tokens) => panic!("{source:?} scanned to {tokens:?}"),
+            Err(errors) => errors
+        }
+    }
+
+    // the value of the single literal `source` scans to
+    fn literal(source: &str) -> Literal {
+        match tokens(source).as_slice() {
+            [Token { literal: Some(literal), .. }, _eof] => literal.clone(),
+            tokens => panic!("{source:?} isn't a single literal: {tokens:?}")
+        }
+    }
+
+    fn types(source: &str) -> Vec<TokenType> {
+        tokens(source).into_iter().map(|token| token.token_type).collect()
+    }
+
+    #[test]
+    fn block_comments_are_skipped() {
+        assert!(matches!(types("1 /* two */ 3")[..], [NUMBER, NUMBER, EOF]));
+    }
+
+    #[test]
+    fn block_comments_can_span_lines() {
+        let tokens = tokens("1 /* a\nb\n*/ 2");
+        assert_eq!(tokens[1].lexeme, "2");
+        assert_eq!(tokens[1].line, 3);
+    }
+
+    #[test]
+    fn unterminated_block_comment_is_an_error() {
+        let errors = errors("1 /* never\nclosed");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unterminated block comment.");
+        assert_eq!((errors[0].line, errors[0].column), (1, 3));
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        assert!(matches!(types("/* a /* b */ c */ 1")[..], [NUMBER, EOF]));
+        assert!(matches!(types("/* a /* b /* c */ */ */ 1")[..], [NUMBER, EOF]));
+    }
+
+    #[test]
+    fn unbalanced_nested_comment_is_unterminated() {
+        let errors = errors("/* a /* b */ 1");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unterminated block comment.");
+    }
+
+    #[test]
+    fn escape_sequences_are_interpreted() {
+        assert!(matches!(literal(r#""a\tb""#), Literal::STRING(s) if s == "a\tb"));
+        assert!(matches!(literal(r#""\"q\" \\ \n""#), Literal::STRING(s) if s == "\"q\" \\ \n"));
+    }
+
+    #[test]
+    fn unknown_escape_sequence_is_an_error() {
+        assert_eq!(errors(r#""\q""#)[0].message, "Invalid escape sequence.");
+    }
+
+    #[test]
+    fn scientific_notation() {
+        assert!(matches!(literal("1e3"), Literal::NUMBER(n) if n == 1000.0));
+        assert!(matches!(literal("1.5E2"), Literal::NUMBER(n) if n == 150.0));
+        assert!(matches!(literal("2e-3"), Literal::NUMBER(n) if n == 0.002));
+        assert!(matches!(literal("2e+3"), Literal::NUMBER(n) if n == 2000.0));
+    }
+
+    #[test]
+    fn exponent_needs_digits() {
+        assert_eq!(errors("1e")[0].message, "Expect digits in exponent.");
+        assert_eq!(errors("1e-;")[0].message, "Expect digits in exponent.");
+    }
+
+    #[test]
+    fn hex_literals() {
+        assert!(matches!(literal("0xff"), Literal::INTEGER(255)));
+        assert!(matches!(literal("0x10"), Literal::INTEGER(16)));
+        assert!(matches!(literal("0xAb"), Literal::INTEGER(171)));
+    }
+
+    #[test]
+    fn hex_literal_needs_digits() {
+        assert_eq!(errors("0x")[0].message, "Expect hex digits after '0x'.");
+    }
+
+    #[test]
+    fn underscores_separate_digits() {
+        assert!(matches!(literal("1_000"), Literal::INTEGER(1000)));
+        assert!(matches!(literal("2.5_5"), Literal::NUMBER(n) if n == 2.55));
+    }
+
+    #[test]
+    fn leading_underscore_makes_an_identifier() {
+        assert!(matches!(types("_1")[..], [IDENTIFIER, EOF]));
+    }
+
+    #[test]
+    fn underscores_must_sit_between_digits() {
+        assert_eq!(errors("1_")[0].message, "Underscore must separate digits.");
+        assert_eq!(errors("1__2")[0].message, "Underscore must separate digits.");
+    }
+
+    #[test]
+    fn large_input_scans_like_its_parts() {
+        let part = "var x = 1.5 * (y + 0xff); // note\nprint \"s\" >= \"t\" and !x;\n/* c */ fun f(a, b) { return a ** b; }\n";
+        let lines = part.lines().count();
+        let expected = tokens(part);
+        let copies = 2000;
+
+        let tokens = tokens(&part.repeat(copies));
+        assert_eq!(tokens.len(), (expected.len() - 1) * copies + 1);
+        // the same tokens each time round, only further down
+        for (i, token) in tokens[..tokens.len() - 1].iter().enumerate() {
+            let original = &expected[i % (expected.len() - 1)];
+            let copy = i / (expected.len() - 1);
+            assert_eq!((&token.token_type, &token.lexeme), (&original.token_type, &original.lexeme));
+            assert_eq!(token.line, original.line + copy * lines);
+        }
+    }
+
+    #[test]
+    fn non_ascii_strings_and_comments_scan() {
+        let tokens = tokens("// ünïcödé comment\nprint \"café ☕\"; /* 日本 */ x");
+        assert!(matches!(&tokens[1].literal, Some(Literal::STRING(s)) if s == "café ☕"));
+        assert_eq!(tokens[3].lexeme, "x");
+        assert_eq!(tokens[3].line, 2);
+    }
+
+    #[test]
+    fn non_ascii_outside_a_string_is_an_error() {
+        let errors = errors("var é = 1;");
+        assert_eq!(errors[0].message, "Unexpected character.");
+        assert_eq!(errors[0].column, 5);
+    }
+
+    #[test]
+    fn tokens_carry_their_column() {
+        let tokens = tokens("var answer = 42;\n  print answer;");
+        let positions: Vec<_> = tokens.iter().map(|token| (token.line, token.column)).collect();
+        assert_eq!(positions, [(1, 1), (1, 5), (1, 12), (1, 14), (1, 16), (2, 3), (2, 9), (2, 15), (2, 16)]);
+    }
+
+    #[test]
+    fn columns_count_characters_not_bytes() {
+        let tokens = tokens("\"café ☕\"; /* 日本 */ x");
+        assert_eq!((tokens[1].lexeme.as_str(), tokens[1].column), (";", 9));
+        assert_eq!((tokens[2].lexeme.as_str(), tokens[2].column), ("x", 20));
+    }
+
+    #[test]
+    fn scan_errors_carry_their_position() {
+        let errors = errors("var a = 1;\nvar b = @;");
+        assert_eq!((errors[0].line, errors[0].column), (2, 9));
+        assert_eq!(errors[0].to_string(), "[line 2, col 9] Error: Unexpected character.");
+    }
+
+    #[test]
+    fn unterminated_string_is_a_single_error() {
+        let errors = errors("print \"never closed;");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unterminated string starting here.");
+    }
+
+    #[test]
+    fn unterminated_string_reports_the_line_it_opened_on() {
+        let errors = errors("var a = 1;\nvar s = \"opens here\nand\nkeeps\ngoing");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "[line 2, col 9] Error: Unterminated string starting here.");
+    }
+
+    // malformed numbers are diagnosed and skipped rather than panicking, and
+    // scanning carries on to report anything after them
+    #[test]
+    fn malformed_number_literals_are_diagnosed() {
+        let errors = errors("print 0xFFFFFFFFFFFFFFFFFF;\nprint 2e;\nprint 1__0;");
+        let messages: Vec<_> = errors.iter().map(|error| (error.line, error.message)).collect();
+        assert_eq!(messages, [
+            (1, "Hex literal is too large."),
+            (2, "Expect digits in exponent."),
+            (3, "Underscore must separate digits.")
+        ]);
+        assert_eq!(errors[0].to_string(), "[line 1, col 7] Error: Hex literal is too large.");
+    }
+
+    // across scans too, so a name the REPL scans on one line finds the
+    // variable a previous line defined
+    #[test]
+    fn occurrences_of_an_identifier_share_a_symbol() {
+        let symbols: Vec<_> = tokens("var total = 1; total = total + 1;").into_iter()
+            .filter(|token| token.lexeme == "total")
+            .map(|token| token.symbol.expect("identifiers are interned"))
+            .collect();
+        assert_eq!(symbols.len(), 3);
+        assert!(symbols.iter().all(|symbol| *symbol == symbols[0]));
+        assert_eq!(tokens("total;")[0].symbol, Some(symbols[0]));
+        assert_eq!(crate::interner::resolve(symbols[0]), "total");
+    }
+
+    #[test]
+    fn only_names_are_interned() {
+        let interned: Vec<_> = tokens("this.x = \"s\" + 1;").into_iter()
+            .map(|token| token.symbol.is_some())
+            .collect();
+        assert_eq!(interned, [true, false, true, false, false, false, false, false, false]);
+    }
+
+    #[test]
+    fn iterating_matches_scan_tokens() {
+        let source = "fun f(a) {\n  return a * 2.5; // twice and a half\n}\nprint f(\"x\");";
+        let streamed: Vec<_> = Scanner::new(source.to_string())
+            .map(|token| token.expect("source scans"))
+            .map(|token| (token.token_type, token.lexeme, token.line, token.column))
+            .collect();
+        let scanned: Vec<_> = tokens(source).into_iter()
+            .map(|token| (token.token_type, token.lexeme, token.line, token.column))
+            .collect();
+        assert_eq!(streamed, scanned);
+    }
+
+    #[test]
+    fn iterating_is_lazy_and_yields_errors_in_place() {
+        let mut scanner = Scanner::new("1 @ 2".to_string());
+        assert!(matches!(scanner.next(), Some(Ok(Token { token_type: NUMBER, .. }))));
+        assert!(matches!(scanner.next(), Some(Err(ScanError { message: "Unexpected character.", .. }))));
+        assert!(matches!(scanner.next(), Some(Ok(Token { token_type: NUMBER, .. }))));
+        assert!(matches!(scanner.next(), Some(Ok(Token { token_type: EOF, .. }))));
+        assert!(scanner.next().is_none());
+    }
+}
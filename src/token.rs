@@ -1,31 +1,58 @@
+use crate::interner::{self, Symbol};
 use crate::token_type::{TokenType, Literal};
 use std::fmt;
 
+// Where a token, or the expression starting with it, sits in the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
+    // interned for names that are looked up: identifiers, `this` and `super`
+    pub symbol: Option<Symbol>,
     pub literal: Option<Literal>,
-    pub line: usize
+    pub line: usize,
+    pub column: usize
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: Option<Literal>, line: usize) -> Self {
+    pub fn new(token_type: TokenType, lexeme: String, literal: Option<Literal>, line: usize, column: usize) -> Self {
+        let symbol = match token_type {
+            TokenType::IDENTIFIER | TokenType::THIS | TokenType::SUPER => Some(interner::intern(&lexeme)),
+            _ => None
+        };
         Self {
             token_type,
             lexeme,
+            symbol,
             literal,
-            line
+            line,
+            column
         }
     }
 
-    pub fn to_string(&self) -> String {
-        format!("{} {} {}", self.token_type, self.lexeme, self.literal.clone().unwrap_or(Literal::IDENTIFIER) )
+    // the symbol a token without one would have, so any token can name a
+    // variable
+    pub fn symbol(&self) -> Symbol {
+        self.symbol.unwrap_or_else(|| interner::intern(&self.lexeme))
+    }
+
+    pub fn span(&self) -> Span {
+        Span { line: self.line, column: self.column }
     }
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {} {}", self.token_type, self.lexeme, self.literal.clone().unwrap())
+        // type, lexeme, literal value (`null` if none) and line, as jlox prints them
+        match &self.literal {
+            Some(literal) => write!(f, "{} {} {} {}", self.token_type, self.lexeme, literal, self.line),
+            None => write!(f, "{} {} null {}", self.token_type, self.lexeme, self.line)
+        }
     }
 }
\ No newline at end of file
@@ -1,16 +1,22 @@
+use std::fmt;
 use strum_macros::Display;
 
-#[derive(Display, Clone, Debug, PartialEq, Eq)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Display, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TokenType {
     // Single-character tokens.
   LEFT_PAREN, RIGHT_PAREN, LEFT_BRACE, RIGHT_BRACE,
+  LEFT_BRACKET, RIGHT_BRACKET,
   COMMA, DOT, MINUS, PLUS, SEMICOLON, SLASH, STAR,
+  QUESTION, COLON, AMPERSAND, PIPE, CARET,
 
   // One or two character tokens.
+  STAR_STAR, TILDE, TILDE_SLASH, DOT_DOT_DOT,
+  PLUS_EQUAL, MINUS_EQUAL, STAR_EQUAL, SLASH_EQUAL,
   BANG, BANG_EQUAL,
   EQUAL, EQUAL_EQUAL,
-  GREATER, GREATER_EQUAL,
-  LESS, LESS_EQUAL,
+  GREATER, GREATER_EQUAL, GREATER_GREATER,
+  LESS, LESS_EQUAL, LESS_LESS,
 
   // Literals.
   IDENTIFIER, STRING, NUMBER,
@@ -18,11 +24,24 @@ pub enum TokenType {
   // Keywords.
   AND, CLASS, ELSE, FALSE, FUN, FOR, IF, NIL, OR,
   PRINT, RETURN, SUPER, THIS, TRUE, VAR, WHILE,
+  BREAK, CONTINUE, SWITCH, CASE, DEFAULT,
 
   EOF
 }
 
-#[derive(Display, Debug, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone)]
 pub enum Literal {
-    IDENTIFIER, STRING(String), NUMBER(f32)
+    STRING(String), NUMBER(f64), INTEGER(i64)
+}
+
+// the value itself; floats keep their point so `2.0` and `2` stay apart
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Literal::STRING(s) => write!(f, "{s}"),
+            Literal::NUMBER(n) => write!(f, "{n:?}"),
+            Literal::INTEGER(i) => write!(f, "{i}")
+        }
+    }
 }
\ No newline at end of file
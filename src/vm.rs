@@ -0,0 +1,897 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use crate::ast::{CallExpression, Expression, FunctionDeclaration, LiteralExpression, Statement};
+use crate::callable::{Arity, NativeFunction};
+use crate::interpreter::{Frame, Interpreter, InterpreterError, RuntimeError, Value};
+use crate::natives::{self, StdinReader};
+use crate::token::{Span, Token};
+use crate::token_type::{Literal, TokenType};
+
+// A bytecode backend: the AST is compiled to a flat list of instructions,
+// which run on a value stack instead of recursing through the tree. It
+// shares Value and its operations with the tree-walker so both give the same
+// results and errors. Each call gets a frame of stack slots for its locals.
+// Classes, and closures over the locals of an enclosing function, aren't
+// compiled yet.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpCode {
+    Constant(usize),
+    Nil,
+    True,
+    False,
+    Pop,
+    // locals live in stack slots, globals by name (a constant index)
+    GetLocal(usize),
+    SetLocal(usize),
+    GetGlobal(usize),
+    DefineGlobal(usize),
+    SetGlobal(usize),
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    IntDivide,
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    Not,
+    Negate,
+    BitNot,
+    Print,
+    // absolute instruction indices
+    Jump(usize),
+    // leaves the condition on the stack
+    JumpIfFalse(usize),
+    // skips the default of the parameter in that slot when the call passed it
+    JumpIfPassed(usize, usize),
+    // the callee sits below its arguments, which become the first locals of
+    // its frame; the span is the callee's, for when it isn't callable
+    Call(usize, Span),
+    // `return f(x);`, with `f` taking over the current frame
+    TailCall(usize, Span),
+    Return,
+    // from that many elements, or key and value pairs, on the stack
+    List(usize),
+    Map(usize),
+    // fails unless the map key on top of the stack is a string
+    MapKey,
+    // with the object's span, and the operator of a compound assignment
+    // like `a[i] += 1`, for errors the tree-walker reports there
+    Index(Span),
+    IndexSet(Span, Option<(TokenType, Span)>)
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<OpCode>,
+    // where each instruction came from, for runtime errors
+    pub spans: Vec<Span>,
+    pub constants: Vec<Value>
+}
+
+impl Chunk {
+    fn emit(&mut self, op: OpCode, span: Span) -> usize {
+        self.code.push(op);
+        self.spans.push(span);
+        self.code.len() - 1
+    }
+
+    fn constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+}
+
+/// A compiled function, kept as a constant of the chunk declaring it.
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub arity: Arity,
+    // the named parameters, not counting a rest parameter
+    pub params: usize,
+    pub rest: bool,
+    pub chunk: Chunk
+}
+
+#[derive(Debug, Clone)]
+pub struct CompileError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[line {}, col {}] Error: {}", self.line, self.column, self.message)
+    }
+}
+
+struct Local {
+    name: String,
+    depth: usize
+}
+
+struct Loop {
+    scope_depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>
+}
+
+/// Compiles resolved statements to a chunk. In the REPL, top-level
+/// expression statements print their value like the tree-walker echoes it.
+pub fn compile(statements: &[Statement], repl: bool) -> Result<Chunk, CompileError> {
+    let mut compiler = Compiler::new(0, repl);
+    for statement in statements {
+        compiler.statement(statement)?;
+    }
+    Ok(compiler.chunk)
+}
+
+struct Compiler {
+    chunk: Chunk,
+    locals: Vec<Local>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    repl: bool
+}
+
+impl Compiler {
+    fn new(scope_depth: usize, repl: bool) -> Self {
+        Self {
+            chunk: Chunk::default(),
+            locals: Vec::new(),
+            scope_depth,
+            loops: Vec::new(),
+            repl
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        match statement {
+            Statement::Expression(e) => {
+                self.expression(e)?;
+                let op = if self.repl && self.scope_depth == 0 { OpCode::Print } else { OpCode::Pop };
+                self.chunk.emit(op, e.span());
+            },
+            Statement::Print(e) => {
+                self.expression(e)?;
+                self.chunk.emit(OpCode::Print, e.span());
+            },
+            Statement::VarDeclaration(v) => {
+                match &v.initializer {
+                    Some(initializer) => self.expression(initializer)?,
+                    None => { self.chunk.emit(OpCode::Nil, v.name.span()); }
+                }
+                self.define(&v.name);
+            },
+            Statement::If(s) => {
+                let span = s.condition.span();
+                self.expression(&s.condition)?;
+                let then_jump = self.chunk.emit(OpCode::JumpIfFalse(0), span);
+                self.chunk.emit(OpCode::Pop, span);
+                self.statement(&s.then_branch)?;
+                let else_jump = self.chunk.emit(OpCode::Jump(0), span);
+
+                self.patch(then_jump);
+                self.chunk.emit(OpCode::Pop, span);
+                if let Some(else_branch) = &s.else_branch {
+                    self.statement(else_branch)?;
+                }
+                self.patch(else_jump);
+            },
+            Statement::While(s) => {
+                let span = s.condition.span();
+                let start = self.chunk.code.len();
+                self.expression(&s.condition)?;
+                let exit = self.chunk.emit(OpCode::JumpIfFalse(0), span);
+                self.chunk.emit(OpCode::Pop, span);
+
+                self.loops.push(Loop { scope_depth: self.scope_depth, breaks: Vec::new(), continues: Vec::new() });
+                self.statement(&s.body)?;
+                let continue_target = self.chunk.code.len();
+                if let Some(increment) = &s.increment {
+                    self.expression(increment)?;
+                    self.chunk.emit(OpCode::Pop, increment.span());
+                }
+                self.chunk.emit(OpCode::Jump(start), span);
+
+                self.patch(exit);
+                self.chunk.emit(OpCode::Pop, span);
+                let finished = self.loops.pop().expect("loop pushed above");
+                for jump in finished.breaks {
+                    self.patch(jump);
+                }
+                for jump in finished.continues {
+                    self.patch_to(jump, continue_target);
+                }
+            },
+            Statement::Switch(s) => {
+                let span = s.subject.span();
+                // the subject sits in an unnamed local while the cases compare against it
+                self.begin_scope();
+                self.expression(&s.subject)?;
+                self.locals.push(Local { name: String::new(), depth: self.scope_depth });
+                let subject = self.locals.len() - 1;
+
+                let mut exits = Vec::new();
+                for (value, body) in &s.cases {
+                    self.chunk.emit(OpCode::GetLocal(subject), span);
+                    self.expression(value)?;
+                    self.chunk.emit(OpCode::Equal, value.span());
+                    let next = self.chunk.emit(OpCode::JumpIfFalse(0), value.span());
+                    self.chunk.emit(OpCode::Pop, value.span());
+                    self.block(body, span)?;
+                    exits.push(self.chunk.emit(OpCode::Jump(0), span));
+
+                    self.patch(next);
+                    self.chunk.emit(OpCode::Pop, value.span());
+                }
+                if let Some(default) = &s.default {
+                    self.block(default, span)?;
+                }
+                for exit in exits {
+                    self.patch(exit);
+                }
+                self.end_scope(span);
+            },
+            Statement::Block(statements) => {
+                self.block(statements, statement.span().unwrap_or(Span { line: 0, column: 0 }))?;
+            },
+            Statement::Break(keyword) | Statement::Continue(keyword) => {
+                // the parser only accepts these inside a loop
+                let Some(scope_depth) = self.loops.last().map(|l| l.scope_depth) else {
+                    return Err(Self::error(keyword.span(), "Can't use 'break' or 'continue' outside a loop."))
+                };
+                // drop the locals of the scopes being jumped out of
+                let unwound = self.locals.iter().rev().take_while(|local| local.depth > scope_depth).count();
+                for _ in 0..unwound {
+                    self.chunk.emit(OpCode::Pop, keyword.span());
+                }
+                let jump = self.chunk.emit(OpCode::Jump(0), keyword.span());
+                let current = self.loops.last_mut().expect("checked above");
+                if matches!(statement, Statement::Break(_)) {
+                    current.breaks.push(jump);
+                } else {
+                    current.continues.push(jump);
+                }
+            },
+            Statement::Function(f) => {
+                let function = Value::VmFunction(Rc::new(Self::function(f)?));
+                let constant = self.chunk.constant(function);
+                self.chunk.emit(OpCode::Constant(constant), f.name.span());
+                self.define(&f.name);
+            },
+            Statement::Class(c) => return Err(Self::unsupported(c.name.span(), "Classes")),
+            Statement::Return(r) => {
+                match &r.value {
+                    Some(Expression::Call(call)) => self.call(call, true)?,
+                    Some(value) => {
+                        self.expression(value)?;
+                        self.chunk.emit(OpCode::Return, r.keyword.span());
+                    },
+                    None => {
+                        self.chunk.emit(OpCode::Nil, r.keyword.span());
+                        self.chunk.emit(OpCode::Return, r.keyword.span());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // the value on top of the stack becomes the variable: a global at the
+    // top level, otherwise the local already sitting in that slot
+    fn define(&mut self, name: &Token) {
+        if self.scope_depth == 0 {
+            let constant = self.chunk.constant(Value::String(name.lexeme.clone()));
+            self.chunk.emit(OpCode::DefineGlobal(constant), name.span());
+        } else {
+            self.locals.push(Local { name: name.lexeme.clone(), depth: self.scope_depth });
+        }
+    }
+
+    // The parameters take the first slots of the frame. An optional one the
+    // call left out starts as nil and a prologue evaluates its default, which
+    // is compiled when only the parameters before it are locals, since it
+    // can see those but not its own or later ones.
+    fn function(declaration: &FunctionDeclaration) -> Result<Function, CompileError> {
+        let mut compiler = Self::new(1, false);
+        let params = declaration.params.len();
+        let required = params - declaration.defaults.len();
+        for (slot, param) in declaration.params.iter().enumerate() {
+            if let Some(default) = slot.checked_sub(required).map(|optional| &declaration.defaults[optional]) {
+                let span = default.span();
+                let skip = compiler.chunk.emit(OpCode::JumpIfPassed(slot, 0), span);
+                compiler.expression(default)?;
+                compiler.chunk.emit(OpCode::SetLocal(slot), span);
+                compiler.chunk.emit(OpCode::Pop, span);
+                compiler.patch(skip);
+            }
+            compiler.locals.push(Local { name: param.lexeme.clone(), depth: 1 });
+        }
+        if let Some(rest) = &declaration.rest {
+            compiler.locals.push(Local { name: rest.lexeme.clone(), depth: 1 });
+        }
+        for statement in &declaration.body {
+            compiler.statement(statement)?;
+        }
+        // falling off the end returns nil
+        compiler.chunk.emit(OpCode::Nil, declaration.name.span());
+        compiler.chunk.emit(OpCode::Return, declaration.name.span());
+
+        Ok(Function {
+            name: declaration.name.lexeme.clone(),
+            arity: Arity::of(declaration),
+            params,
+            rest: declaration.rest.is_some(),
+            chunk: compiler.chunk
+        })
+    }
+
+    fn call(&mut self, expression: &CallExpression, tail: bool) -> Result<(), CompileError> {
+        self.expression(&expression.callee)?;
+        for argument in &expression.arguments {
+            self.expression(argument)?;
+        }
+        let (count, callee) = (expression.arguments.len(), expression.callee.span());
+        let op = if tail { OpCode::TailCall(count, callee) } else { OpCode::Call(count, callee) };
+        self.chunk.emit(op, expression.paren.span());
+        Ok(())
+    }
+
+    fn block(&mut self, statements: &[Statement], span: Span) -> Result<(), CompileError> {
+        self.begin_scope();
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        self.end_scope(span);
+        Ok(())
+    }
+
+    fn begin_scope(&mut self) {
+        self.scope_depth += 1;
+    }
+
+    // pops the locals of the scope being left
+    fn end_scope(&mut self, span: Span) {
+        self.scope_depth -= 1;
+        while self.locals.last().is_some_and(|local| local.depth > self.scope_depth) {
+            self.locals.pop();
+            self.chunk.emit(OpCode::Pop, span);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<(), CompileError> {
+        match expression {
+            Expression::Literal(literal) => self.literal(literal)?,
+            Expression::Grouping(e) => self.expression(&e.expression)?,
+            Expression::Unary(e) => {
+                self.expression(&e.right)?;
+                let op = match e.operator.token_type {
+                    TokenType::MINUS => OpCode::Negate,
+                    TokenType::BANG => OpCode::Not,
+                    _ => OpCode::BitNot
+                };
+                // unary errors point at the operand, as in the tree-walker
+                self.chunk.emit(op, e.right.span());
+            },
+            Expression::Binary(e) if e.operator.token_type == TokenType::COMMA => {
+                self.expression(&e.left)?;
+                self.chunk.emit(OpCode::Pop, e.operator.span());
+                self.expression(&e.right)?;
+            },
+            Expression::Binary(e) => {
+                self.expression(&e.left)?;
+                self.expression(&e.right)?;
+                let op = match e.operator.token_type {
+                    TokenType::PLUS => OpCode::Add,
+                    TokenType::MINUS => OpCode::Subtract,
+                    TokenType::STAR => OpCode::Multiply,
+                    TokenType::SLASH => OpCode::Divide,
+                    TokenType::TILDE_SLASH => OpCode::IntDivide,
+                    TokenType::STAR_STAR => OpCode::Power,
+                    TokenType::AMPERSAND => OpCode::BitAnd,
+                    TokenType::PIPE => OpCode::BitOr,
+                    TokenType::CARET => OpCode::BitXor,
+                    TokenType::LESS_LESS => OpCode::ShiftLeft,
+                    TokenType::GREATER_GREATER => OpCode::ShiftRight,
+                    TokenType::GREATER => OpCode::Greater,
+                    TokenType::GREATER_EQUAL => OpCode::GreaterEqual,
+                    TokenType::LESS => OpCode::Less,
+                    TokenType::LESS_EQUAL => OpCode::LessEqual,
+                    TokenType::BANG_EQUAL => OpCode::NotEqual,
+                    TokenType::EQUAL_EQUAL => OpCode::Equal,
+                    _ => return Err(Self::error(e.operator.span(), "Unknown binary operator."))
+                };
+                self.chunk.emit(op, e.operator.span());
+            },
+            Expression::Logical(e) => {
+                let span = e.operator.span();
+                self.expression(&e.left)?;
+                // the left operand is the result when it decides the outcome
+                if e.operator.token_type == TokenType::AND {
+                    let end = self.chunk.emit(OpCode::JumpIfFalse(0), span);
+                    self.chunk.emit(OpCode::Pop, span);
+                    self.expression(&e.right)?;
+                    self.patch(end);
+                } else {
+                    let else_jump = self.chunk.emit(OpCode::JumpIfFalse(0), span);
+                    let end = self.chunk.emit(OpCode::Jump(0), span);
+                    self.patch(else_jump);
+                    self.chunk.emit(OpCode::Pop, span);
+                    self.expression(&e.right)?;
+                    self.patch(end);
+                }
+            },
+            Expression::Ternary(e) => {
+                let span = e.condition.span();
+                self.expression(&e.condition)?;
+                let else_jump = self.chunk.emit(OpCode::JumpIfFalse(0), span);
+                self.chunk.emit(OpCode::Pop, span);
+                self.expression(&e.then_expr)?;
+                let end = self.chunk.emit(OpCode::Jump(0), span);
+                self.patch(else_jump);
+                self.chunk.emit(OpCode::Pop, span);
+                self.expression(&e.else_expr)?;
+                self.patch(end);
+            },
+            // the resolver left globals without a slot
+            Expression::Variable(v) => {
+                let op = match v.slot {
+                    Some(_) => OpCode::GetLocal(self.local(&v.name)?),
+                    None => OpCode::GetGlobal(self.chunk.constant(Value::String(v.name.lexeme.clone())))
+                };
+                self.chunk.emit(op, v.name.span());
+            },
+            Expression::Assign(a) => {
+                self.expression(&a.value)?;
+                let op = match a.slot {
+                    Some(_) => OpCode::SetLocal(self.local(&a.name)?),
+                    None => OpCode::SetGlobal(self.chunk.constant(Value::String(a.name.lexeme.clone())))
+                };
+                self.chunk.emit(op, a.name.span());
+            },
+            Expression::Call(e) => self.call(e, false)?,
+            Expression::List(e) => {
+                for element in &e.elements {
+                    self.expression(element)?;
+                }
+                self.chunk.emit(OpCode::List(e.elements.len()), e.bracket.span());
+            },
+            Expression::Map(e) => {
+                for (key, value) in &e.entries {
+                    self.expression(key)?;
+                    self.chunk.emit(OpCode::MapKey, key.span());
+                    self.expression(value)?;
+                }
+                self.chunk.emit(OpCode::Map(e.entries.len()), e.brace.span());
+            },
+            Expression::Index(e) => {
+                self.expression(&e.object)?;
+                self.expression(&e.index)?;
+                self.chunk.emit(OpCode::Index(e.object.span()), e.index.span());
+            },
+            Expression::IndexSet(e) => {
+                self.expression(&e.object)?;
+                self.expression(&e.index)?;
+                self.expression(&e.value)?;
+                let operator = e.operator.as_ref().map(|operator| (operator.token_type, operator.span()));
+                self.chunk.emit(OpCode::IndexSet(e.object.span(), operator), e.index.span());
+            },
+            Expression::Get(_) | Expression::Set(_) => return Err(Self::unsupported(expression.span(), "Properties")),
+            Expression::This(_) | Expression::Super(_) => return Err(Self::unsupported(expression.span(), "Classes"))
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, literal: &LiteralExpression) -> Result<(), CompileError> {
+        let (op, span) = match literal {
+            LiteralExpression::Boolean(t) if t.token_type == TokenType::TRUE => (OpCode::True, t.span()),
+            LiteralExpression::Boolean(t) => (OpCode::False, t.span()),
+            LiteralExpression::Null(t) => (OpCode::Nil, t.span()),
+            LiteralExpression::String(t) => match &t.literal {
+                Some(Literal::STRING(s)) => (OpCode::Constant(self.chunk.constant(Value::String(s.clone()))), t.span()),
+                _ => return Err(Self::error(t.span(), "Invalid string literal."))
+            },
+            LiteralExpression::Number(t) => match t.literal {
+                Some(Literal::NUMBER(n)) => (OpCode::Constant(self.chunk.constant(Value::Number(n))), t.span()),
+                Some(Literal::INTEGER(i)) => (OpCode::Constant(self.chunk.constant(Value::Integer(i))), t.span()),
+                _ => return Err(Self::error(t.span(), "Invalid number literal."))
+            }
+        };
+        self.chunk.emit(op, span);
+        Ok(())
+    }
+
+    // innermost first, so shadowing picks the nearest declaration. A local
+    // the resolver found that isn't this function's belongs to one around it.
+    fn local(&self, name: &Token) -> Result<usize, CompileError> {
+        self.locals.iter().rposition(|local| local.name == name.lexeme)
+            .ok_or_else(|| Self::unsupported(name.span(), "Closures"))
+    }
+
+    fn patch(&mut self, jump: usize) {
+        self.patch_to(jump, self.chunk.code.len());
+    }
+
+    fn patch_to(&mut self, jump: usize, target: usize) {
+        self.chunk.code[jump] = match self.chunk.code[jump] {
+            OpCode::JumpIfFalse(_) => OpCode::JumpIfFalse(target),
+            OpCode::JumpIfPassed(slot, _) => OpCode::JumpIfPassed(slot, target),
+            _ => OpCode::Jump(target)
+        };
+    }
+
+    fn error(span: Span, message: impl Into<String>) -> CompileError {
+        CompileError { line: span.line, column: span.column, message: message.into() }
+    }
+
+    fn unsupported(span: Span, what: &str) -> CompileError {
+        Self::error(span, format!("{what} aren't supported by the VM yet."))
+    }
+}
+
+// A call in progress. Its locals are the stack from `base`, where its first
+// argument went, and `line` is where it was called from.
+struct CallFrame {
+    function: Rc<Function>,
+    ip: usize,
+    base: usize,
+    // how many arguments the call passed, before any defaults
+    passed: usize,
+    line: usize,
+    tail_calls: usize
+}
+
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    // below the user's globals, so `var clock` shadows the native rather
+    // than replacing it
+    natives: HashMap<String, Value>,
+    globals: HashMap<String, Value>,
+    out: Rc<RefCell<dyn Write>>,
+    pub coerce_concat: bool,
+    // calls deeper than this fail with a stack overflow, as in the tree-walker
+    pub max_call_depth: usize
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self::with_writer(Rc::new(RefCell::new(io::stdout())))
+    }
+
+    pub fn with_writer(out: Rc<RefCell<impl Write + 'static>>) -> Self {
+        Self::with_io(StdinReader::new(), out)
+    }
+
+    pub fn with_io(input: impl BufRead + 'static, out: Rc<RefCell<impl Write + 'static>>) -> Self {
+        let out: Rc<RefCell<dyn Write>> = out;
+        let natives = natives::standard(input, Rc::clone(&out)).into_iter()
+            .map(|native| (native.name.clone(), Value::NativeFunction(native)))
+            .collect();
+        Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            natives,
+            globals: HashMap::new(),
+            out,
+            coerce_concat: false,
+            max_call_depth: 1000
+        }
+    }
+
+    pub fn define_native(&mut self, name: &str, arity: impl Into<Arity>, func: impl Fn(Vec<Value>) -> Result<Value, RuntimeError> + 'static) {
+        let native = NativeFunction::new(name, arity, func);
+        self.natives.insert(name.to_string(), Value::NativeFunction(native));
+    }
+
+    /// Runs `chunk`, keeping globals for the next one as the REPL expects.
+    pub fn run(&mut self, chunk: Chunk) -> Result<(), InterpreterError> {
+        self.stack.clear();
+        // the top level runs in a frame of its own, which isn't a call
+        let script = Function { name: String::new(), arity: Arity::from(0), params: 0, rest: false, chunk };
+        self.frames = vec![CallFrame { function: Rc::new(script), ip: 0, base: 0, passed: 0, line: 0, tail_calls: 0 }];
+        let result = self.execute().map_err(|mut error| {
+            error.trace = self.frames[1..].iter().rev()
+                .map(|frame| Frame { function: frame.function.name.clone(), line: frame.line, tail_calls: frame.tail_calls })
+                .collect();
+            error
+        });
+        self.frames.clear();
+        result
+    }
+
+    fn execute(&mut self) -> Result<(), InterpreterError> {
+        // the running frame's state, written back to it across calls
+        let (mut function, mut ip, mut base) = self.resume();
+        loop {
+            let Some(&op) = function.chunk.code.get(ip) else {
+                // the end of the script, functions always return
+                return Ok(())
+            };
+            let span = function.chunk.spans[ip];
+            ip += 1;
+            match op {
+                OpCode::Constant(index) => self.stack.push(function.chunk.constants[index].clone()),
+                OpCode::Nil => self.stack.push(Value::Null),
+                OpCode::True => self.stack.push(Value::Boolean(true)),
+                OpCode::False => self.stack.push(Value::Boolean(false)),
+                OpCode::Pop => { self.pop(); },
+                OpCode::GetLocal(slot) => self.stack.push(self.stack[base + slot].clone()),
+                OpCode::SetLocal(slot) => self.stack[base + slot] = self.peek().clone(),
+                OpCode::GetGlobal(name) => {
+                    let name = Self::name(&function.chunk, name);
+                    let value = self.globals.get(&name).or_else(|| self.natives.get(&name)).cloned()
+                        .ok_or_else(|| InterpreterError::at(span, RuntimeError::UndefinedVariable { name }))?;
+                    self.stack.push(value);
+                },
+                OpCode::DefineGlobal(name) => {
+                    let value = self.pop();
+                    self.globals.insert(Self::name(&function.chunk, name), value);
+                },
+                OpCode::SetGlobal(name) => {
+                    let name = Self::name(&function.chunk, name);
+                    let value = self.peek().clone();
+                    match self.globals.get_mut(&name) {
+                        Some(global) => *global = value,
+                        None if self.natives.contains_key(&name) => return Err(InterpreterError::at(span, RuntimeError::AssignToNative { name })),
+                        None => return Err(InterpreterError::at(span, RuntimeError::UndefinedVariable { name }))
+                    }
+                },
+                OpCode::Equal => self.binary(span, Value::equals)?,
+                OpCode::NotEqual => self.binary(span, Value::not_equals)?,
+                OpCode::Greater => self.binary(span, Value::greater)?,
+                OpCode::GreaterEqual => self.binary(span, Value::greater_equal)?,
+                OpCode::Less => self.binary(span, Value::less)?,
+                OpCode::LessEqual => self.binary(span, Value::less_equal)?,
+                OpCode::Add if self.coerce_concat => self.binary(span, Value::add_coerced)?,
+                OpCode::Add => self.binary(span, Value::add)?,
+                OpCode::Subtract => self.binary(span, Value::subtract)?,
+                OpCode::Multiply => self.binary(span, Value::multiply)?,
+                OpCode::Divide => self.binary(span, Value::divide)?,
+                OpCode::IntDivide => self.binary(span, Value::int_divide)?,
+                OpCode::Power => self.binary(span, Value::power)?,
+                OpCode::BitAnd => self.binary(span, Value::bit_and)?,
+                OpCode::BitOr => self.binary(span, Value::bit_or)?,
+                OpCode::BitXor => self.binary(span, Value::bit_xor)?,
+                OpCode::ShiftLeft => self.binary(span, |left, right| left.shift(right, true))?,
+                OpCode::ShiftRight => self.binary(span, |left, right| left.shift(right, false))?,
+                OpCode::Not => self.unary(span, Value::not)?,
+                OpCode::Negate => self.unary(span, Value::negate)?,
+                OpCode::BitNot => self.unary(span, Value::bit_not)?,
+                OpCode::Print => {
+                    let value = self.pop();
+                    writeln!(self.out.borrow_mut(), "{value}")
+                        .map_err(|e| InterpreterError::at(span, RuntimeError::Output(e.to_string())))?;
+                },
+                OpCode::Jump(target) => ip = target,
+                OpCode::JumpIfFalse(target) => {
+                    if !self.peek().is_truthy() {
+                        ip = target;
+                    }
+                },
+                OpCode::JumpIfPassed(slot, target) => {
+                    if self.frames.last().is_some_and(|frame| frame.passed > slot) {
+                        ip = target;
+                    }
+                },
+                OpCode::Call(count, callee) => {
+                    self.save(ip);
+                    self.call(count, span.line, callee)?;
+                    (function, ip, base) = self.resume();
+                },
+                OpCode::TailCall(count, callee) => {
+                    self.save(ip);
+                    if !self.tail_call(count, span.line, callee)? {
+                        self.return_value();
+                    }
+                    (function, ip, base) = self.resume();
+                },
+                OpCode::Return => {
+                    self.return_value();
+                    (function, ip, base) = self.resume();
+                },
+                OpCode::List(count) => {
+                    let elements = self.stack.split_off(self.stack.len() - count);
+                    self.stack.push(Value::List(Rc::new(RefCell::new(elements))));
+                },
+                OpCode::Map(count) => {
+                    let entries = self.stack.split_off(self.stack.len() - 2 * count);
+                    let mut map = HashMap::new();
+                    let mut entries = entries.into_iter();
+                    // MapKey made sure the keys are strings
+                    while let (Some(Value::String(key)), Some(value)) = (entries.next(), entries.next()) {
+                        map.insert(key, value);
+                    }
+                    self.stack.push(Value::Map(Rc::new(RefCell::new(map))));
+                },
+                OpCode::MapKey => {
+                    let key = Interpreter::map_key(self.pop(), span)?;
+                    self.stack.push(Value::String(key));
+                },
+                OpCode::Index(object_span) => {
+                    let index = self.pop();
+                    let object = self.pop();
+                    let value = match object {
+                        Value::List(list) => {
+                            let list = list.borrow();
+                            list[Interpreter::list_index(&index, list.len(), span)?].clone()
+                        },
+                        Value::Map(map) => {
+                            let key = Interpreter::map_key(index, span)?;
+                            map.borrow().get(&key).cloned().unwrap_or(Value::Null)
+                        },
+                        _ => return Err(InterpreterError::at(object_span, RuntimeError::NotIndexable))
+                    };
+                    self.stack.push(value);
+                },
+                OpCode::IndexSet(object_span, operator) => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let object = self.pop();
+                    let value = self.index_set((object, object_span), (index, span), value, operator)?;
+                    self.stack.push(value);
+                }
+            }
+        }
+    }
+
+    fn resume(&self) -> (Rc<Function>, usize, usize) {
+        let frame = self.frames.last().expect("the script's frame is never popped");
+        (Rc::clone(&frame.function), frame.ip, frame.base)
+    }
+
+    fn save(&mut self, ip: usize) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.ip = ip;
+        }
+    }
+
+    fn call(&mut self, count: usize, line: usize, callee_span: Span) -> Result<(), InterpreterError> {
+        let callee = self.stack.len() - 1 - count;
+        match self.stack[callee].clone() {
+            Value::NativeFunction(native) => {
+                Interpreter::check_arity(native.arity, count, line)?;
+                let arguments = self.stack.split_off(callee + 1);
+                self.pop();
+                let result = native.call(arguments).map_err(|kind| InterpreterError::new(line, kind))?;
+                self.stack.push(result);
+            },
+            Value::VmFunction(function) => {
+                Interpreter::check_arity(function.arity, count, line)?;
+                // the script's frame isn't a call
+                if self.frames.len() > self.max_call_depth {
+                    return Err(InterpreterError::new(line, RuntimeError::StackOverflow))
+                }
+                self.arguments(&function, count);
+                self.frames.push(CallFrame { function, ip: 0, base: callee + 1, passed: count, line, tail_calls: 0 });
+            },
+            _ => return Err(InterpreterError::at(callee_span, RuntimeError::NotCallable))
+        }
+        Ok(())
+    }
+
+    // A Lox function replaces the current frame, so tail-recursive functions
+    // run in constant stack as they do in the tree-walker. Anything else is
+    // called as usual, and false returned for its result to be returned.
+    fn tail_call(&mut self, count: usize, line: usize, callee_span: Span) -> Result<bool, InterpreterError> {
+        let callee = self.stack.len() - 1 - count;
+        let Value::VmFunction(function) = self.stack[callee].clone() else {
+            self.call(count, line, callee_span)?;
+            return Ok(false)
+        };
+        Interpreter::check_arity(function.arity, count, line)?;
+
+        let frame = self.frames.last_mut().expect("only functions return");
+        // the new callee and its arguments take the place of the old
+        self.stack.drain(frame.base - 1..callee);
+        frame.function = Rc::clone(&function);
+        frame.ip = 0;
+        frame.passed = count;
+        frame.line = line;
+        frame.tail_calls += 1;
+        self.arguments(&function, count);
+        Ok(true)
+    }
+
+    // Fills the rest of the parameter slots: optional parameters the call
+    // left out are nil until the function's prologue runs their defaults, and
+    // arguments past the named parameters are gathered into the rest one.
+    fn arguments(&mut self, function: &Function, count: usize) {
+        for _ in count..function.params {
+            self.stack.push(Value::Null);
+        }
+        if function.rest {
+            let rest = self.stack.split_off(self.stack.len() - count.saturating_sub(function.params));
+            self.stack.push(Value::List(Rc::new(RefCell::new(rest))));
+        }
+    }
+
+    // drops the returning frame's callee, arguments and locals for its result
+    fn return_value(&mut self) {
+        let result = self.pop();
+        let frame = self.frames.pop().expect("only functions return");
+        self.stack.truncate(frame.base - 1);
+        self.stack.push(result);
+    }
+
+    fn index_set(&self, (object, object_span): (Value, Span), (index, index_span): (Value, Span), mut value: Value, operator: Option<(TokenType, Span)>) -> Result<Value, InterpreterError> {
+        match object {
+            Value::List(list) => {
+                let i = Interpreter::list_index(&index, list.borrow().len(), index_span)?;
+                if let Some(operator) = operator {
+                    let current = list.borrow()[i].clone();
+                    value = self.compound(operator, current, value)?;
+                }
+                list.borrow_mut()[i] = value.clone();
+            },
+            Value::Map(map) => {
+                let key = Interpreter::map_key(index, index_span)?;
+                if let Some(operator) = operator {
+                    let current = map.borrow().get(&key).cloned().unwrap_or(Value::Null);
+                    value = self.compound(operator, current, value)?;
+                }
+                map.borrow_mut().insert(key, value.clone());
+            },
+            _ => return Err(InterpreterError::at(object_span, RuntimeError::NotIndexable))
+        }
+        Ok(value)
+    }
+
+    // the operators of `+=`, `-=`, `*=` and `/=`
+    fn compound(&self, (operator, span): (TokenType, Span), left: Value, right: Value) -> Result<Value, InterpreterError> {
+        let result = match operator {
+            TokenType::PLUS if self.coerce_concat => left.add_coerced(right),
+            TokenType::PLUS => left.add(right),
+            TokenType::MINUS => left.subtract(right),
+            TokenType::STAR => left.multiply(right),
+            _ => left.divide(right)
+        };
+        result.map_err(|kind| InterpreterError::at(span, kind))
+    }
+
+    fn binary(&mut self, span: Span, op: fn(&Value, Value) -> Result<Value, RuntimeError>) -> Result<(), InterpreterError> {
+        let right = self.pop();
+        let left = self.pop();
+        self.stack.push(op(&left, right).map_err(|kind| InterpreterError::at(span, kind))?);
+        Ok(())
+    }
+
+    fn unary(&mut self, span: Span, op: fn(&Value) -> Result<Value, RuntimeError>) -> Result<(), InterpreterError> {
+        let operand = self.pop();
+        self.stack.push(op(&operand).map_err(|kind| InterpreterError::at(span, kind))?);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("compiler keeps the stack balanced")
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("compiler keeps the stack balanced")
+    }
+
+    fn name(chunk: &Chunk, index: usize) -> String {
+        chunk.constants[index].to_string()
+    }
+}
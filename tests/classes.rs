@@ -0,0 +1,88 @@
+mod common;
+
+use common::{output, resolve, runtime_error};
+
+#[test]
+fn a_class_prints_as_its_name() {
+    assert_eq!(output("class Bagel {} print Bagel;"), "Bagel\n");
+    assert_eq!(output("class Greeter { greet() { print \"hi\"; } } print Greeter;"), "Greeter\n");
+}
+
+#[test]
+fn calling_a_class_makes_an_instance() {
+    assert_eq!(output("class Bagel {} var bagel = Bagel(); print bagel;"), "Bagel instance\n");
+}
+
+#[test]
+fn reading_a_missing_property_is_an_error() {
+    assert_eq!(runtime_error("class Point {} Point().z;"), "Undefined property 'z'.");
+}
+
+#[test]
+fn fields_read_back_what_was_stored() {
+    assert_eq!(output("class Point {} var p = Point(); p.x = 1; p.y = \"two\"; print p.x; print p.y;"), "1\ntwo\n");
+}
+
+#[test]
+fn setting_a_field_yields_the_value_and_overwrites() {
+    assert_eq!(output("class Box {} var b = Box(); print b.value = 1; b.value = b.value + 1; print b.value;"), "1\n2\n");
+}
+
+#[test]
+fn only_instances_have_fields() {
+    assert_eq!(runtime_error("var n = 3; n.x = 1;"), "Only instances have fields.");
+    assert_eq!(runtime_error("print \"s\".length;"), "Only instances have properties.");
+}
+
+#[test]
+fn methods_reach_their_instance_through_this() {
+    let source = "class Counter { increment() { this.count = this.count + 1; return this; } } var c = Counter(); c.count = 0; c.increment().increment(); print c.count;";
+    assert_eq!(output(source), "2\n");
+}
+
+#[test]
+fn bound_methods_remember_their_instance() {
+    let source = "class Named { say() { print this.name; } } var n = Named(); n.name = \"bound\"; var say = n.say; say();";
+    assert_eq!(output(source), "bound\n");
+}
+
+#[test]
+fn this_outside_a_class_is_an_error() {
+    assert!(resolve("print this;").is_none());
+    assert!(resolve("fun f() { return this; }").is_none());
+}
+
+#[test]
+fn subclasses_inherit_methods() {
+    let source = "class Animal { speak() { return \"...\"; } name() { return \"animal\"; } } class Dog < Animal { speak() { return \"woof\"; } } var d = Dog(); print d.speak(); print d.name();";
+    assert_eq!(output(source), "woof\nanimal\n");
+}
+
+#[test]
+fn super_calls_the_superclass_method() {
+    let source = "class A { greet() { return \"A\"; } } class B < A { greet() { return super.greet() + \"B\"; } } class C < B { greet() { return super.greet() + \"C\"; } } print C().greet();";
+    assert_eq!(output(source), "ABC\n");
+}
+
+#[test]
+fn inheritance_errors() {
+    assert!(resolve("class A < A {}").is_none());
+    assert!(resolve("class A { f() { super.f(); } }").is_none());
+    assert_eq!(runtime_error("var NotAClass = 1; class A < NotAClass {}"), "Superclass must be a class.");
+}
+
+#[test]
+fn init_runs_when_instantiating() {
+    assert_eq!(output("class Point { init(x, y) { this.x = x; this.y = y; } } var p = Point(1, 2); print p.x + p.y;"), "3\n");
+    assert_eq!(runtime_error("class Point { init(x, y) {} } Point(1);"), "Expected 2 arguments but got 1.");
+}
+
+#[test]
+fn init_returns_its_instance() {
+    assert_eq!(output("class A { init() { this.n = 1; return; } } var a = A(); print a.init() == a;"), "true\n");
+}
+
+#[test]
+fn returning_a_value_from_init_is_an_error() {
+    assert!(resolve("class A { init() { return 1; } }").is_none());
+}
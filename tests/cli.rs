@@ -0,0 +1,253 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn lox(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox_interpreter"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("interpreter starts");
+    child.stdin.take().expect("stdin is piped").write_all(stdin.as_bytes()).expect("stdin is written");
+    child.wait_with_output().expect("interpreter finishes")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+// a script in the temp directory, named after the test writing it
+fn script(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(format!("rlox-{}-{name}.lox", std::process::id()));
+    std::fs::write(&path, source).expect("script is written");
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn scripts_do_not_echo() {
+    let output = lox(&[&script("scripts_do_not_echo", "1 + 2;")], "");
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn tokens_are_only_dumped_with_the_flag() {
+    let path = script("tokens", "print 1;");
+    assert_eq!(stdout(&lox(&[&path], "")), "1\n");
+
+    // and then nothing runs
+    let dumped = stdout(&lox(&["--tokens", &path], ""));
+    assert!(dumped.lines().next().is_some_and(|line| line.starts_with("PRINT")), "{dumped}");
+    assert!(!dumped.lines().any(|line| line == "1"), "{dumped}");
+}
+
+#[test]
+fn exit_codes() {
+    let status = |name, source| lox(&[&script(name, source)], "").status.code();
+    assert_eq!(status("exit_codes_ok", "1 + 2;"), Some(0));
+    assert_eq!(status("exit_codes_parse", "1 +;"), Some(65));
+    assert_eq!(status("exit_codes_scan", "@;"), Some(65));
+    assert_eq!(status("exit_codes_runtime", "-\"a\";"), Some(70));
+    assert_eq!(lox(&["a", "b"], "").status.code(), Some(64));
+}
+
+#[test]
+fn static_errors_point_at_the_source() {
+    let output = lox(&[&script("static_caret", "var a = 1;\n\tprint a +;\n")], "");
+    assert_eq!(output.status.code(), Some(65));
+    // the tab is kept so the caret lines up
+    assert_eq!(stderr(&output), "[line 2, col 11] Error at ';': Expect expression.\n 2 | \tprint a +;\n   | \t         ^\n");
+}
+
+const UNUSED: &str = "\
+var global = 1;
+fun f() {
+  var unused = 1;
+  var used = 2;
+  var _ignored = 3;
+  return used;
+}
+print f();
+";
+
+#[test]
+fn warns_about_unused_locals() {
+    let output = lox(&["--warnings", &script("warns_about_unused_locals", UNUSED)], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output).lines().last(), Some("2"));
+    assert_eq!(stderr(&output), "[line 3, col 7] Warning: unused variable 'unused'.\n 3 |   var unused = 1;\n   |       ^\n");
+}
+
+#[test]
+fn warnings_are_opt_in() {
+    let output = lox(&[&script("warnings_are_opt_in", UNUSED)], "");
+    assert_eq!(stdout(&output).lines().last(), Some("2"));
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn warns_about_unreachable_code() {
+    let source = "fun f() {\n  return 1;\n  print 2;\n}\nwhile (true) { break; print 3; }\nprint f();\n";
+    let output = lox(&["--warnings", &script("warns_about_unreachable_code", source)], "");
+    assert_eq!(output.status.code(), Some(0));
+    let warnings: Vec<_> = stderr(&output).lines().filter(|line| line.contains("Warning")).map(str::to_string).collect();
+    assert_eq!(warnings, ["[line 3, col 9] Warning: unreachable code.", "[line 5, col 29] Warning: unreachable code."]);
+}
+
+#[test]
+fn runtime_error_in_a_script_is_reported() {
+    let output = lox(&[&script("runtime_error_in_a_script_is_reported", "print 1;\nprint nil - 1;\nprint 2;")], "");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(stdout(&output), "1\n");
+    assert!(stderr(&output).starts_with("[line 2, col 11] Runtime error: Operands must be numbers.\n"), "{}", stderr(&output));
+    assert!(!stderr(&output).contains("panicked"));
+}
+
+#[test]
+fn ast_flag_prints_the_tree_without_running() {
+    let output = lox(&["--ast", &script("ast_flag", "(1 + 2) * 3;\nprint 4;")], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "(* (group (+ 1 2)) 3)\n(print 4)\n");
+}
+
+#[test]
+fn ast_flag_reports_parse_errors() {
+    let output = lox(&["--ast", &script("ast_flag_errors", "(1 + ;")], "");
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).starts_with("[line 1, col 6] Error at ';': Expect expression.\n"), "{}", stderr(&output));
+}
+
+#[test]
+fn e_flag_runs_its_argument() {
+    let output = lox(&["-e", "print 1 + 2;"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "3\n");
+
+    assert_eq!(lox(&["-e", "print nil - 1;"], "").status.code(), Some(70));
+    assert_eq!(lox(&["-e", "print ;"], "").status.code(), Some(65));
+}
+
+#[test]
+fn e_flag_needs_source() {
+    let output = lox(&["-e"], "");
+    assert_eq!(output.status.code(), Some(64));
+    assert!(stderr(&output).starts_with("Expected source after '-e'.\nUsage: "), "{}", stderr(&output));
+}
+
+#[test]
+fn piped_stdin_runs_as_one_program() {
+    // no prompt, and a statement may span lines
+    let output = lox(&[], "var a = 1;\nprint a +\n  2;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "3\n");
+
+    assert_eq!(lox(&[], "print nil - 1;").status.code(), Some(70));
+}
+
+#[test]
+fn trace_flag_logs_each_statement_to_stderr() {
+    let output = lox(&["--trace", &script("trace_flag", "var a = 1;\nprint a + 1;")], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "2\n");
+    // each statement before it runs, then what it produced
+    assert_eq!(stderr(&output), "[line 1] (var a 1)\n[line 1] => 1\n[line 2] (print (+ a 1))\n[line 2] => 2\n");
+}
+
+#[test]
+fn scripts_still_need_semicolons() {
+    let output = lox(&["-e", "3 * 4"], "");
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn unbounded_recursion_reports_a_stack_overflow() {
+    let output = lox(&["-e", "fun f() { return 1 + f(); } f();"], "");
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr(&output).starts_with("[line 1] Runtime error: Stack overflow.\n"), "{}", stderr(&output));
+}
+
+#[test]
+fn missing_script_is_reported() {
+    let output = lox(&["does/not/exist.lox"], "");
+    assert_eq!(output.status.code(), Some(66));
+    assert!(stderr(&output).starts_with("Could not read 'does/not/exist.lox': "), "{}", stderr(&output));
+    assert!(!stderr(&output).contains("panicked"));
+}
+
+const TOKENS: &str = "\
+VAR var null 1
+IDENTIFIER x null 1
+EQUAL = null 1
+STRING \"hi\" hi 1
+SEMICOLON ; null 1
+PRINT print null 2
+NUMBER 2.5 2.5 2
+STAR * null 2
+NUMBER 4 4 2
+SEMICOLON ; null 2
+EOF  null 2
+";
+
+#[test]
+fn tokens_flag_prints_the_token_stream() {
+    let source = "var x = \"hi\";\nprint 2.5 * 4;";
+    let output = lox(&["--tokens", &script("tokens_flag", source)], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), TOKENS);
+
+    // piped in as well as from a file
+    let output = lox(&["--tokens"], source);
+    assert_eq!(stdout(&output), TOKENS);
+}
+
+#[test]
+fn unreachable_code_warns_only_with_the_flag() {
+    let path = script("unreachable", "fun f() {\n  return 1;\n  print 2;\n}\nprint f();");
+
+    let output = lox(&[&path], "");
+    assert_eq!(stdout(&output), "1\n");
+    assert_eq!(stderr(&output), "");
+
+    let output = lox(&["--warnings", &path], "");
+    assert_eq!(stdout(&output), "1\n");
+    assert!(stderr(&output).starts_with("[line 3, col 9] Warning: unreachable code.\n"), "{}", stderr(&output));
+}
+
+#[test]
+fn unreachable_code_warns_once_per_run() {
+    let path = script("unreachable_run", "while (true) {\n  break;\n  print 1;\n  print 2;\n}");
+    let output = lox(&["--warnings", &path], "");
+    assert_eq!(stderr(&output).matches("unreachable code").count(), 1);
+}
+
+#[test]
+fn coerce_concat_flag() {
+    let path = script("coerce_concat", "print \"count: \" + 3;");
+    assert_eq!(lox(&[&path], "").status.code(), Some(70));
+
+    let output = lox(&["--coerce-concat", &path], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "count: 3\n");
+
+    let output = lox(&["--coerce-concat", "--vm", &path], "");
+    assert_eq!(stdout(&output), "count: 3\n");
+}
+
+#[test]
+fn runtime_errors_point_at_the_source() {
+    let path = script("runtime_caret", "fun f(x) {\n  return x - \"a\";\n}\nprint f(1);\n");
+    let output = lox(&[&path], "");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(stderr(&output), "\
+[line 2, col 12] Runtime error: Operands must be numbers.
+ 2 |   return x - \"a\";
+   |            ^
+  in f() at line 4
+");
+}
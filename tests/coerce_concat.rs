@@ -0,0 +1,31 @@
+mod common;
+
+use common::{output, output_with, run_with, runtime_error};
+
+fn coerced(source: &str) -> String {
+    output_with(source, |interpreter| interpreter.coerce_concat = true)
+}
+
+#[test]
+fn strict_by_default() {
+    assert_eq!(runtime_error("\"count: \" + 3;"), "Operands must be two numbers or two strings.");
+    assert_eq!(output("print \"a\" + \"b\";"), "ab\n");
+}
+
+#[test]
+fn coerces_numbers_and_booleans() {
+    assert_eq!(coerced("print \"count: \" + 3;"), "count: 3\n");
+    assert_eq!(coerced("print 2.5 + \" apples\";"), "2.5 apples\n");
+    assert_eq!(coerced("print \"ok: \" + true;"), "ok: true\n");
+}
+
+#[test]
+fn still_adds_numbers() {
+    assert_eq!(coerced("print 1 + 2;"), "3\n");
+}
+
+#[test]
+fn leaves_nil_an_error() {
+    let (_, result) = run_with("\"a\" + nil;", |interpreter| interpreter.coerce_concat = true);
+    assert!(result.is_err());
+}
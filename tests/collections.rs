@@ -0,0 +1,77 @@
+mod common;
+
+use common::{output, runtime_error};
+
+#[test]
+fn list_literals_print_their_elements() {
+    assert_eq!(output("print [1, \"two\", nil, [3]]; print [];"), "[1, two, nil, [3]]\n[]\n");
+}
+
+#[test]
+fn list_elements_are_read_by_index() {
+    assert_eq!(output("var l = [\"a\", \"b\", [\"c\"]]; print l[0]; print l[1 + 1][0];"), "a\nc\n");
+}
+
+#[test]
+fn reading_past_either_end_is_an_error() {
+    assert_eq!(runtime_error("[1, 2][2];"), "List index out of bounds.");
+    assert_eq!(runtime_error("[1, 2][-1];"), "List index out of bounds.");
+    assert_eq!(runtime_error("[1][0.5];"), "List index must be an integer.");
+    assert_eq!(runtime_error("var s = \"s\"; s[0];"), "Only lists and maps can be indexed.");
+}
+
+#[test]
+fn lists_are_mutable_and_shared() {
+    assert_eq!(output("var a = [1, 2]; var b = a; b[0] = 9; print a;"), "[9, 2]\n");
+    assert_eq!(runtime_error("var a = [1]; a[1] = 2;"), "List index out of bounds.");
+}
+
+// cut short where they recur, by print and str() alike
+#[test]
+fn a_list_containing_itself_prints() {
+    assert_eq!(output("var l = [1]; push(l, l); print l; print str(l);"), "[1, [...]]\n[1, [...]]\n");
+    assert_eq!(output("var l = []; print [l, l];"), "[[], []]\n");
+}
+
+#[test]
+fn push_pop_and_len() {
+    assert_eq!(output("var l = []; push(l, 1); push(l, 2); print len(l); print pop(l); print l; print len(l);"), "2\n2\n[1]\n1\n");
+}
+
+#[test]
+fn len_counts_the_characters_of_a_string() {
+    assert_eq!(output("print len(\"\"); print len(\"héllo\");"), "0\n5\n");
+}
+
+#[test]
+fn popping_an_empty_list_is_an_error() {
+    assert_eq!(runtime_error("pop([]);"), "Can't pop from an empty list.");
+    assert_eq!(runtime_error("push(1, 2);"), "Can only push onto a list.");
+    assert_eq!(runtime_error("len(1);"), "Can only take the length of a list or string.");
+}
+
+#[test]
+fn map_literals_print_sorted_by_key() {
+    assert_eq!(output("print {\"b\": 2, \"a\": [1]}; print {};"), "{a: [1], b: 2}\n{}\n");
+}
+
+#[test]
+fn map_entries_are_read_and_written_by_key() {
+    assert_eq!(output("var m = {\"a\": 1}; m[\"b\"] = 2; m[\"a\"] = m[\"a\"] + 10; print m[\"a\"]; print m[\"b\"]; print keys(m);"), "11\n2\n[a, b]\n");
+}
+
+#[test]
+fn a_missing_key_reads_as_nil() {
+    assert_eq!(output("print {\"a\": 1}[\"z\"];"), "nil\n");
+}
+
+#[test]
+fn map_keys_must_be_strings() {
+    assert_eq!(runtime_error("var m = {1: 2};"), "Map keys must be strings.");
+    assert_eq!(runtime_error("var m = {}; m[nil] = 1;"), "Map keys must be strings.");
+}
+
+#[test]
+fn a_map_containing_itself_prints() {
+    assert_eq!(output("var m = {\"a\": 1}; m[\"m\"] = m; m[\"l\"] = [m]; print m;"), "{a: 1, l: [{...}], m: {...}}\n");
+}
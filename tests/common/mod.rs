@@ -0,0 +1,99 @@
+// helpers shared by the integration tests; each test file uses only some
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::panic;
+use std::rc::Rc;
+use std::thread;
+
+use rlox::ast::Statement;
+use rlox::{Interpreter, InterpreterError, Parser, Resolver, Scanner};
+
+/// Runs `f` on a thread with room for `max_call_depth` calls, which the
+/// 2 MiB stack a test thread gets doesn't have in a debug build.
+pub fn with_deep_stack<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    let thread = thread::Builder::new().stack_size(64 * 1024 * 1024).spawn(f).expect("thread starts");
+    thread.join().unwrap_or_else(|panic| panic::resume_unwind(panic))
+}
+
+/// A writer for an interpreter, which the caller can read back with
+/// `printed` after running.
+pub fn writer() -> Rc<RefCell<Vec<u8>>> {
+    Rc::new(RefCell::new(Vec::new()))
+}
+
+/// What was written to `out`.
+pub fn printed(out: &RefCell<Vec<u8>>) -> String {
+    String::from_utf8(out.borrow().clone()).expect("output is utf-8")
+}
+
+/// Runs `source` in a fresh interpreter set up by `configure`, returning what
+/// it printed along with the first runtime error, so output from before an
+/// error can be checked too.
+pub fn run_with(source: &str, configure: impl FnOnce(&mut Interpreter)) -> (String, Result<(), InterpreterError>) {
+    let out = writer();
+    let mut interpreter = Interpreter::with_writer(Rc::clone(&out));
+    configure(&mut interpreter);
+    let result = run_in(&mut interpreter, source);
+    (printed(&out), result)
+}
+
+/// Like `run_with`, for statements that have already been parsed and
+/// resolved.
+pub fn run_statements(statements: &[Statement]) -> (String, Result<(), InterpreterError>) {
+    let out = writer();
+    let result = Interpreter::with_writer(Rc::clone(&out)).interpret(statements, false);
+    (printed(&out), result)
+}
+
+/// What `source` printed, reading `input` for `input()`, which must run
+/// without error.
+pub fn output_reading(source: &str, input: &'static str) -> String {
+    let out = writer();
+    let mut interpreter = Interpreter::with_io(input.as_bytes(), Rc::clone(&out));
+    if let Err(error) = run_in(&mut interpreter, source) {
+        panic!("{source:?} failed: {}", error.kind);
+    }
+    printed(&out)
+}
+
+fn run_in(interpreter: &mut Interpreter, source: &str) -> Result<(), InterpreterError> {
+    let statements = resolve(source).unwrap_or_else(|| panic!("{source:?} doesn't parse"));
+    statements.iter().try_for_each(|statement| interpreter.execute(statement).map(|_| ()))
+}
+
+/// The statements `source` parses and resolves to, or None if any stage
+/// reported an error.
+pub fn resolve(source: &str) -> Option<Vec<Statement>> {
+    let tokens = Scanner::new(source.to_string()).scan_tokens().ok()?;
+    let (mut statements, errors) = Parser::new(tokens).parse();
+    let mut resolver = Resolver::new();
+    resolver.resolve(&mut statements);
+    if !errors.is_empty() || !resolver.errors.is_empty() { None } else { Some(statements) }
+}
+
+/// What `source` printed, which must run without error.
+pub fn output(source: &str) -> String {
+    output_with(source, |_| ())
+}
+
+/// Like `output`, after `configure` has set the interpreter up.
+pub fn output_with(source: &str, configure: impl FnOnce(&mut Interpreter)) -> String {
+    match run_with(source, configure) {
+        (printed, Ok(())) => printed,
+        (_, Err(error)) => panic!("{source:?} failed: {}", error.kind)
+    }
+}
+
+/// The runtime error `source` fails with.
+pub fn error(source: &str) -> InterpreterError {
+    match run_with(source, |_| ()) {
+        (_, Err(error)) => error,
+        (printed, Ok(())) => panic!("{source:?} succeeded, printing {printed:?}")
+    }
+}
+
+/// The message of the runtime error `source` fails with.
+pub fn runtime_error(source: &str) -> String {
+    error(source).kind.to_string()
+}
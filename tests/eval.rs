@@ -0,0 +1,93 @@
+mod common;
+
+use common::with_deep_stack;
+use rlox::{eval, eval_with, Interpreter, LoxError, RuntimeError, Value};
+
+#[test]
+fn evaluates_a_trailing_expression() {
+    assert!(matches!(eval("1 + 2"), Ok(Value::Integer(3))));
+}
+
+#[test]
+fn returns_the_last_statement_value() {
+    assert!(matches!(eval("var a = 2; a * 2"), Ok(Value::Integer(4))));
+}
+
+#[test]
+fn scan_errors_carry_their_diagnostics() {
+    match eval("\"open") {
+        Err(LoxError::Scan(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].line, 1);
+        },
+        other => panic!("expected a scan error, got {other:?}")
+    }
+}
+
+#[test]
+fn parse_errors_carry_their_diagnostics() {
+    match eval("print (1;\nprint 2 +;") {
+        Err(LoxError::Parse(errors)) => assert_eq!(errors.len(), 2),
+        other => panic!("expected parse errors, got {other:?}")
+    }
+}
+
+#[test]
+fn resolver_errors_are_parse_errors() {
+    match eval("return 1;") {
+        Err(LoxError::Parse(errors)) => assert_eq!(errors[0].message, "Can't return from top-level code."),
+        other => panic!("expected a parse error, got {other:?}")
+    }
+}
+
+#[test]
+fn runtime_errors_carry_their_kind() {
+    match eval("var a = 1;\na / 0") {
+        Err(LoxError::Runtime(error)) => assert_eq!((error.line, error.kind), (2, RuntimeError::DivisionByZero)),
+        other => panic!("expected a runtime error, got {other:?}")
+    }
+}
+
+fn is_stack_overflow(result: Result<Value, LoxError>) -> bool {
+    matches!(result, Err(LoxError::Runtime(error)) if error.kind == RuntimeError::StackOverflow)
+}
+
+#[test]
+fn unbounded_recursion_is_a_stack_overflow() {
+    assert!(with_deep_stack(|| is_stack_overflow(eval("fun f() { return 1 + f(); } f()"))));
+}
+
+#[test]
+fn unbounded_recursion_through_nested_statements_is_a_stack_overflow() {
+    let source = "fun f(n) { var i = 0; while (i < 1) { if (n > 0) { var r = [f(n + 1)][0]; return r; } i = i + 1; } } f(1)";
+    assert!(with_deep_stack(move || is_stack_overflow(eval(source))));
+}
+
+// max_call_depth is the only limit by default, not however much stack the
+// first few calls happened to take
+#[test]
+fn recursion_within_max_call_depth_runs() {
+    let source = "fun count(n) { if (n == 0) return 0; return 1 + count(n - 1); } count(500)";
+    assert!(with_deep_stack(|| matches!(eval(source), Ok(Value::Integer(500)))));
+}
+
+// on the 2 MiB stack a test thread gets, which can't take 1000 calls in a
+// debug build
+#[test]
+fn max_stack_size_stops_recursion_before_the_stack_runs_out() {
+    let mut interpreter = Interpreter::new();
+    interpreter.max_stack_size = Some(1024 * 1024);
+    assert!(is_stack_overflow(eval_with(&mut interpreter, "fun f() { return 1 + f(); } f()")));
+}
+
+#[test]
+fn call_depth_is_configurable() {
+    let mut interpreter = Interpreter::new();
+    interpreter.max_call_depth = 10;
+    let count = "fun count(n) { if (n == 0) return 0; return 1 + count(n - 1); }";
+    eval_with(&mut interpreter, count).expect("declares count");
+
+    // count(9) is ten calls deep
+    assert!(matches!(eval_with(&mut interpreter, "count(9)"), Ok(Value::Integer(9))));
+    assert!(is_stack_overflow(eval_with(&mut interpreter, "count(10)")));
+}
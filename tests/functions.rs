@@ -0,0 +1,76 @@
+mod common;
+
+use common::{error, output, runtime_error};
+use rlox::callable::Arity;
+use rlox::RuntimeError;
+
+const GREET: &str = "fun greet(name, greeting = \"Hello\") { return greeting + \", \" + name; }";
+
+#[test]
+fn omitted_arguments_take_their_default() {
+    assert_eq!(output(&format!("{GREET} print greet(\"Ada\");")), "Hello, Ada\n");
+}
+
+#[test]
+fn supplied_arguments_override_the_default() {
+    assert_eq!(output(&format!("{GREET} print greet(\"Ada\", \"Hi\");")), "Hi, Ada\n");
+}
+
+// defaults are evaluated in the call each time they're needed
+#[test]
+fn defaults_are_evaluated_at_call_time() {
+    assert_eq!(output("var p = \"!\"; fun f(a = p) { return a; } print f(); p = \"?\"; print f();"), "!\n?\n");
+}
+
+#[test]
+fn defaults_see_the_parameters_before_them() {
+    assert_eq!(output("fun g(a, b = a + 1, c = a + b) { print c; } g(1); g(1, 5); g(1, 5, 0);"), "3\n6\n0\n");
+    assert_eq!(output("fun h(a, b = [a], ...rest) { return [b, rest]; } print h(1); print h(1, 2, 3);"), "[[1], []]\n[2, [3]]\n");
+}
+
+// their own parameter and later ones aren't bound yet, so the name is
+// looked up outside the function
+#[test]
+fn defaults_cannot_see_later_parameters() {
+    assert_eq!(runtime_error("fun g(a = b, b = 1) {} g();"), "Undefined variable 'b'.");
+    assert_eq!(output("var b = \"outer\"; fun g(a = b, b = 1) { print a; } g();"), "outer\n");
+}
+
+const SUM: &str = "fun sum(...nums) { var total = 0; for (var i = 0; i < len(nums); i += 1) total += nums[i]; return total; }";
+
+#[test]
+fn rest_parameter_collects_any_number_of_arguments() {
+    assert_eq!(output(&format!("{SUM} print sum();")), "0\n");
+    assert_eq!(output(&format!("{SUM} print sum(4);")), "4\n");
+    assert_eq!(output(&format!("{SUM} print sum(1, 2, 3, 4);")), "10\n");
+    assert_eq!(output("fun all(...rest) { return rest; } print all(); print all(1, \"a\");"), "[]\n[1, a]\n");
+}
+
+#[test]
+fn rest_parameter_follows_the_fixed_ones() {
+    assert_eq!(output("fun f(first, ...rest) { print first; print rest; } f(1); f(1, 2, 3);"), "1\n[]\n1\n[2, 3]\n");
+    assert_eq!(runtime_error("fun f(first, ...rest) {} f();"), "Expected at least 1 argument but got 0.");
+}
+
+fn arity_mismatch(expected: impl Into<Arity>, got: usize) -> RuntimeError {
+    RuntimeError::ArityMismatch { expected: expected.into(), got }
+}
+
+#[test]
+fn user_functions_reject_too_few_or_too_many_arguments() {
+    assert_eq!(error("fun f(a, b) {} f(1);").kind, arity_mismatch(2, 1));
+    assert_eq!(error("fun f(a, b) {} f(1, 2, 3);").kind, arity_mismatch(2, 3));
+    assert_eq!(runtime_error("fun f(a, b) {} f(1, 2, 3);"), "Expected 2 arguments but got 3.");
+}
+
+#[test]
+fn natives_reject_too_few_or_too_many_arguments() {
+    assert_eq!(error("len();").kind, arity_mismatch(1, 0));
+    assert_eq!(error("push([], 1, 2);").kind, arity_mismatch(2, 3));
+    assert_eq!(runtime_error("len();"), "Expected 1 argument but got 0.");
+}
+
+#[test]
+fn arity_errors_report_the_call_line() {
+    assert_eq!(error("fun f(a, b) {}\n\nf(1);").line, 3);
+}
@@ -0,0 +1,226 @@
+mod common;
+
+use common::{error, output, resolve, run_with, runtime_error};
+
+use rlox::{eval_with, Interpreter, RuntimeError, Value};
+
+#[test]
+fn print_writes_to_the_interpreter_sink() {
+    assert_eq!(output("print 1 + 2; print \"a\";"), "3\na\n");
+}
+
+#[test]
+fn print_of_each_value() {
+    assert_eq!(output("print nil; print true; print 2.5; print \"s\";"), "nil\ntrue\n2.5\ns\n");
+}
+
+#[test]
+fn functions_are_values() {
+    assert_eq!(output("fun add(a, b) { print a + b; } print add; var f = add; print f == add;"), "<fn add>\ntrue\n");
+}
+
+#[test]
+fn calls_pass_their_arguments_in_order() {
+    assert_eq!(output("fun hello() { print \"hello\"; } hello();"), "hello\n");
+    assert_eq!(output("fun join(a, b, c) { print a + b + c; } join(\"x\", \"y\", \"z\");"), "xyz\n");
+}
+
+#[test]
+fn calls_must_match_the_parameter_count() {
+    assert_eq!(runtime_error("fun f(a, b) {} f(1);"), "Expected 2 arguments but got 1.");
+    assert_eq!(runtime_error("fun f(a, b) {} f(1, 2, 3);"), "Expected 2 arguments but got 3.");
+}
+
+#[test]
+fn only_functions_can_be_called() {
+    assert_eq!(runtime_error("\"not a function\"();"), "Can only call functions and classes.");
+}
+
+#[test]
+fn return_gives_the_call_its_value() {
+    assert_eq!(output("fun add(a, b) { return a + b; } print add(1, 2);"), "3\n");
+    assert_eq!(output("fun nothing() { return; } print nothing();"), "nil\n");
+}
+
+#[test]
+fn return_leaves_a_loop_early() {
+    let source = "fun first_over(limit) { for (var i = 0; i < 100; i = i + 1) { if (i * i > limit) return i; } return nil; } print first_over(10); print first_over(10000);";
+    assert_eq!(output(source), "4\nnil\n");
+}
+
+#[test]
+fn return_leaves_nested_blocks_and_loops() {
+    let source = "fun f() { while (true) { { var x = \"out\"; while (true) { return x; } } } } print f();";
+    assert_eq!(output(source), "out\n");
+}
+
+#[test]
+fn break_leaves_only_the_innermost_loop() {
+    let source = "for (var i = 0; i < 3; i = i + 1) { for (var j = 0; j < 3; j = j + 1) { if (j == 1) break; print i * 10 + j; } }";
+    assert_eq!(output(source), "0\n10\n20\n");
+}
+
+#[test]
+fn break_outside_a_loop_is_an_error() {
+    assert!(resolve("break;").is_none());
+    // a function body is outside the loop around it
+    assert!(resolve("while (true) { fun f() { break; } }").is_none());
+}
+
+#[test]
+fn continue_skips_to_the_next_iteration() {
+    let while_loop = "var sum = 0; var i = 0; var even = true; while (i < 10) { i = i + 1; even = !even; if (!even) continue; sum = sum + i; } print sum;";
+    assert_eq!(output(while_loop), "30\n");
+    // a for loop's increment still runs
+    let for_loop = "var sum = 0; var even = true; for (var i = 1; i <= 10; i = i + 1) { even = !even; if (!even) continue; sum = sum + i; } print sum;";
+    assert_eq!(output(for_loop), "30\n");
+}
+
+#[test]
+fn continue_outside_a_loop_is_an_error() {
+    assert!(resolve("continue;").is_none());
+}
+
+#[test]
+fn errors_point_at_the_failing_subexpression() {
+    let error = error("print 1 +\n  (2 * nil);");
+    assert_eq!((error.line, error.column), (2, Some(6)));
+    assert_eq!(error.kind, RuntimeError::TypeMismatch("Operands must be numbers."));
+}
+
+#[test]
+fn runtime_errors_say_what_went_wrong() {
+    let kind = |source| error(source).kind;
+    assert_eq!(kind("print -\"a\";"), RuntimeError::TypeMismatch("Operand must be a number."));
+    assert_eq!(kind("print 1 / 0;"), RuntimeError::DivisionByZero);
+    assert_eq!(kind("print 1 << 64;"), RuntimeError::InvalidShift("Shift amount must be less than 64."));
+    assert_eq!(kind("print missing;"), RuntimeError::UndefinedVariable { name: "missing".to_string() });
+    assert_eq!(kind("class A {} print A().x;"), RuntimeError::UndefinedProperty { name: "x".to_string() });
+    assert_eq!(kind("fun f(a) {} f();"), RuntimeError::ArityMismatch { expected: 1.into(), got: 0 });
+    assert_eq!(kind("\"a\"();"), RuntimeError::NotCallable);
+    assert_eq!(kind("[1][1];"), RuntimeError::IndexOutOfBounds);
+    assert_eq!(kind("pop([]);"), RuntimeError::EmptyList);
+}
+
+// NaN follows IEEE 754, as in the book: it isn't equal to anything,
+// including itself
+#[test]
+fn nan_is_not_equal_to_itself() {
+    assert_eq!(output("var n = (-1) ** 0.5; print n; print n == n; print n != n;"), "NaN\nfalse\ntrue\n");
+    assert_eq!(output("print is_nan((-1) ** 0.5); print is_nan(1); print is_nan(\"NaN\");"), "true\nfalse\nfalse\n");
+}
+
+#[test]
+fn compound_assignment_to_variables() {
+    assert_eq!(output("var a = 10; a += 5; a -= 3; a *= 2; a /= 4; print a;"), "6\n");
+    assert_eq!(output("var s = \"a\"; s += \"b\"; print s; print s += \"c\";"), "ab\nabc\n");
+    assert_eq!(runtime_error("nope += 1;"), "Undefined variable 'nope'.");
+}
+
+#[test]
+fn compound_assignment_to_fields() {
+    assert_eq!(output("class P {} var p = P(); p.x = 1; p.x += 2; p.x *= 10; print p.x; print p.x -= 5;"), "30\n25\n");
+    // the object expression is evaluated once
+    assert_eq!(output("class P {} var p = P(); p.x = 1; var calls = 0; fun get() { calls += 1; return p; } get().x += 4; print p.x; print calls;"), "5\n1\n");
+}
+
+#[test]
+fn closures_share_a_captured_variable() {
+    let source = "
+        var increment; var read;
+        {
+            var count = 0;
+            fun inc() { count += 1; }
+            fun get() { return count; }
+            increment = inc; read = get;
+        }
+        increment(); increment();
+        print read();
+        increment();
+        print read();";
+    assert_eq!(output(source), "2\n3\n");
+}
+
+#[test]
+fn trace_lists_every_active_call() {
+    let error = error("fun inner() {\n  return nil + 1;\n}\nfun outer() {\n  inner();\n}\nouter();");
+    let frames: Vec<_> = error.trace.iter().map(|frame| (frame.function.as_str(), frame.line)).collect();
+    assert_eq!(frames, [("inner", 5), ("outer", 7)]);
+    assert_eq!(error.to_string(), "[line 2, col 14] Runtime error: Operands must be two numbers or two strings.\n  in inner() at line 5\n  in outer() at line 7");
+}
+
+// a low limit keeps the test within the default test thread's stack
+#[test]
+fn unbounded_recursion_is_a_stack_overflow() {
+    let (printed, result) = run_with("print 1; fun f() { return 1 + f(); } f(); print 2;", |interpreter| interpreter.max_call_depth = 50);
+    assert_eq!(printed, "1\n");
+    let error = result.expect_err("recursion overflows");
+    assert_eq!(error.kind, RuntimeError::StackOverflow);
+    assert!(error.to_string().starts_with("[line 1] Runtime error: Stack overflow.\n  in f() at line 1\n  ... repeated"), "{error}");
+}
+
+#[test]
+fn recursion_within_the_limit_runs() {
+    let source = "fun depth(n) { if (n == 0) return 0; return 1 + depth(n - 1); } print depth(40);";
+    assert_eq!(run_with(source, |interpreter| interpreter.max_call_depth = 50).0, "40\n");
+}
+
+// each call in return position replaces its caller's frame
+#[test]
+fn tail_calls_run_in_constant_stack() {
+    let source = "fun count(n) { if (n == 0) return \"done\"; return count(n - 1); } print count(100000);";
+    assert_eq!(run_with(source, |interpreter| interpreter.max_call_depth = 50).0, "done\n");
+    let source = "fun even(n) { if (n == 0) return true; return odd(n - 1); } fun odd(n) { if (n == 0) return false; return even(n - 1); } print even(10001);";
+    assert_eq!(run_with(source, |interpreter| interpreter.max_call_depth = 50).0, "false\n");
+}
+
+const SWITCH: &str = "fun describe(n) { switch (n) { case 1: print \"one\"; case 1 + 1: print \"two\"; print \"(even)\"; default: print \"many\"; } }";
+
+#[test]
+fn switch_runs_only_the_matching_case() {
+    assert_eq!(output(&format!("{SWITCH} describe(1); describe(2);")), "one\ntwo\n(even)\n");
+}
+
+#[test]
+fn switch_falls_back_to_default() {
+    assert_eq!(output(&format!("{SWITCH} describe(7); describe(\"1\");")), "many\nmany\n");
+}
+
+#[test]
+fn switch_without_a_match_or_default_does_nothing() {
+    assert_eq!(output("switch (3) { case 1: print \"one\"; case 2: print \"two\"; } print \"after\";"), "after\n");
+}
+
+#[test]
+fn while_loop_sums_one_to_five() {
+    let source = "var sum = 0; var i = 1; while (i <= 5) { sum = sum + i; i = i + 1; } print sum;";
+    assert_eq!(output(source), "15\n");
+}
+
+#[test]
+fn functions_can_be_called_repeatedly() {
+    let source = "fun add(a, b) { return a + b; } var sum = 0; for (var i = 1; i <= 5; i = i + 1) sum = add(sum, i); print sum;";
+    assert_eq!(output(source), "15\n");
+}
+
+#[test]
+fn globals_shadow_natives() {
+    assert_eq!(output("var clock = 1; print clock;"), "1\n");
+    assert_eq!(output("var len = 1; len = 2; print len;"), "2\n");
+}
+
+#[test]
+fn inner_scopes_still_reach_natives() {
+    assert_eq!(output("{ var clock = 1; } print type(clock);"), "function\n");
+    assert_eq!(output("fun f() { return len(\"abc\"); } { var len = 0; print f(); }"), "3\n");
+}
+
+#[test]
+fn assigning_to_a_native_is_an_error() {
+    assert_eq!(error("len = nil;").kind, RuntimeError::AssignToNative { name: "len".to_string() });
+
+    // and leaves the native in place for the rest of the session
+    let mut interpreter = Interpreter::new();
+    assert!(eval_with(&mut interpreter, "len = nil;").is_err());
+    assert!(matches!(eval_with(&mut interpreter, "len(\"ab\")"), Ok(Value::Integer(2))));
+}
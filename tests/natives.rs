@@ -0,0 +1,135 @@
+mod common;
+
+use common::{error, output, output_reading, output_with, runtime_error};
+use rlox::{eval, RuntimeError, Value};
+
+#[test]
+fn host_functions_can_be_registered() {
+    let printed = output_with("print double; print double == double;", |interpreter| {
+        interpreter.define_native("double", 1, |arguments| match &arguments[0] {
+            Value::Number(n) => Ok(Value::Number(n * 2.0)),
+            _ => Ok(Value::Null)
+        });
+    });
+    assert_eq!(printed, "<native fn>\ntrue\n");
+}
+
+#[test]
+fn clock_is_a_global_native_returning_seconds() {
+    match eval("clock") {
+        Ok(Value::NativeFunction(clock)) => {
+            assert_eq!(clock.arity, 0.into());
+            assert!(matches!(clock.call(vec![]), Ok(Value::Number(n)) if n > 1e9));
+        },
+        other => panic!("expected a native function, got {other:?}")
+    }
+}
+
+#[test]
+fn natives_can_be_called() {
+    let printed = output_with("print twice(21); print twice(1.5);", |interpreter| {
+        interpreter.define_native("twice", 1, |arguments| match &arguments[0] {
+            Value::Integer(i) => Ok(Value::Integer(i * 2)),
+            Value::Number(n) => Ok(Value::Number(n * 2.0)),
+            _ => Ok(Value::Null)
+        });
+    });
+    assert_eq!(printed, "42\n3\n");
+}
+
+#[test]
+fn str_formats_like_print() {
+    assert_eq!(output("print str(42) + \"!\"; print str(1.5); print str(nil); print str([1]);"), "42!\n1.5\nnil\n[1]\n");
+}
+
+#[test]
+fn num_parses_integers_and_floats() {
+    assert!(matches!(eval("num(\"42\")"), Ok(Value::Integer(42))));
+    assert_eq!(output("print num(\"2.5\") * 2;"), "5\n");
+    assert_eq!(output("print num(\" 7 \");"), "7\n");
+}
+
+#[test]
+fn num_of_something_that_isnt_a_number_is_an_error() {
+    assert_eq!(runtime_error("num(\"abc\");"), "Can't convert 'abc' to a number.");
+    assert_eq!(runtime_error("num(1);"), "Can only convert a string to a number.");
+}
+
+#[test]
+fn type_names_each_kind_of_value() {
+    let source = "class A {} fun f() {} print type(1.5); print type(\"s\"); print type(true); print type(nil); print type(f); print type(clock); print type(A); print type(A()); print type([]); print type({});";
+    assert_eq!(output(source), "number\nstring\nbool\nnil\nfunction\nfunction\nclass\ninstance\nlist\nmap\n");
+}
+
+#[test]
+fn input_prompts_and_reads_a_line() {
+    let source = "var first = input(\"First? \"); var last = input(\"Last? \"); print last + \", \" + first; print input(\"More? \");";
+    // line endings are dropped, and the end of input reads as nil
+    assert_eq!(output_reading(source, "Ada\nLovelace\r\n"), "First? Last? Lovelace, Ada\nMore? nil\n");
+}
+
+#[test]
+fn assert_passes_on_truthy_values() {
+    assert_eq!(output("assert(true); assert(1 < 2, \"ordered\"); assert(\"\"); print \"ok\";"), "ok\n");
+}
+
+#[test]
+fn assert_fails_with_its_message() {
+    assert_eq!(runtime_error("assert(false);"), "Assertion failed.");
+    assert_eq!(runtime_error("assert(1 == 2, \"math broke\");"), "math broke");
+    assert_eq!(runtime_error("assert(nil, 5);"), "5");
+}
+
+#[test]
+fn assert_takes_one_or_two_arguments() {
+    assert!(matches!(error("assert();").kind, RuntimeError::ArityMismatch { got: 0, .. }));
+    assert!(matches!(error("assert(true, \"a\", \"b\");").kind, RuntimeError::ArityMismatch { got: 3, .. }));
+    assert_eq!(runtime_error("assert();"), "Expected 1 to 2 arguments but got 0.");
+}
+
+fn value(source: &str) -> Value {
+    eval(source).unwrap_or_else(|error| panic!("{source:?} failed: {error}"))
+}
+
+// sqrt of a negative is NaN rather than an error, following IEEE 754;
+// is_nan() tells it apart
+#[test]
+fn sqrt_of_a_negative_is_nan() {
+    assert_eq!(value("sqrt(16)"), Value::Number(4.0));
+    assert_eq!(value("sqrt(2.25)"), Value::Number(1.5));
+    assert!(value("sqrt(-1)").is_nan());
+}
+
+#[test]
+fn floor_ceil_and_round() {
+    assert_eq!(value("floor(2.7)"), Value::Number(2.0));
+    assert_eq!(value("floor(-2.5)"), Value::Number(-3.0));
+    assert_eq!(value("ceil(2.1)"), Value::Number(3.0));
+    assert_eq!(value("ceil(-2.5)"), Value::Number(-2.0));
+    // halves round away from zero
+    assert_eq!(value("round(2.5)"), Value::Number(3.0));
+    assert_eq!(value("round(-2.5)"), Value::Number(-3.0));
+    assert_eq!(value("round(2.4)"), Value::Number(2.0));
+}
+
+#[test]
+fn abs_keeps_integers_integral() {
+    assert!(matches!(value("abs(-3)"), Value::Integer(3)));
+    assert_eq!(value("abs(-2.5)"), Value::Number(2.5));
+    assert_eq!(value("abs(4)"), Value::Integer(4));
+}
+
+#[test]
+fn pow_raises_to_any_power() {
+    assert_eq!(value("pow(2, 10)"), Value::Number(1024.0));
+    assert_eq!(value("pow(2, -1)"), Value::Number(0.5));
+    assert_eq!(value("pow(2.5, 2)"), Value::Number(6.25));
+}
+
+#[test]
+fn math_natives_need_numbers() {
+    for source in ["sqrt(\"x\");", "floor(nil);", "ceil(true);", "round([]);", "abs(\"1\");"] {
+        assert_eq!(error(source).kind, RuntimeError::TypeMismatch("Argument must be a number."), "{source}");
+    }
+    assert_eq!(runtime_error("pow(2, nil);"), "Operands must be numbers.");
+}
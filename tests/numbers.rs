@@ -0,0 +1,65 @@
+mod common;
+
+use common::{output, runtime_error};
+use rlox::{eval, Value};
+
+#[test]
+fn integer_literals_are_integers() {
+    assert!(matches!(eval("42"), Ok(Value::Integer(42))));
+    assert!(matches!(eval("4.5"), Ok(Value::Number(n)) if n == 4.5));
+}
+
+#[test]
+fn integer_literals_too_big_for_i64_are_floats() {
+    assert!(matches!(eval("9223372036854775807"), Ok(Value::Integer(i64::MAX))));
+    assert!(matches!(eval("9223372036854775808"), Ok(Value::Number(n)) if n == 9223372036854775808.0));
+    assert!(matches!(eval("100000000000000000000000"), Ok(Value::Number(n)) if n == 1e23));
+}
+
+#[test]
+fn mixed_arithmetic_promotes_to_float() {
+    assert!(matches!(eval("1 + 2"), Ok(Value::Integer(3))));
+    assert!(matches!(eval("1 + 2.5"), Ok(Value::Number(n)) if n == 3.5));
+    assert!(matches!(eval("2.5 * 2"), Ok(Value::Number(n)) if n == 5.0));
+    assert!(matches!(eval("7 / 2"), Ok(Value::Number(n)) if n == 3.5));
+}
+
+#[test]
+fn integer_overflow_promotes_to_float() {
+    assert!(matches!(eval("9223372036854775807 + 1"), Ok(Value::Number(_))));
+}
+
+#[test]
+fn integer_division() {
+    assert!(matches!(eval("7 ~/ 2"), Ok(Value::Integer(3))));
+    assert_eq!(output("print 7 ~/ 2 == 3;"), "true\n");
+    assert_eq!(runtime_error("7 ~/ 0;"), "Division by zero.");
+}
+
+#[test]
+fn integers_equal_the_same_float() {
+    assert_eq!(output("print 3 == 3.0;"), "true\n");
+}
+
+#[test]
+fn bitwise_operators() {
+    assert!(matches!(eval("6 & 3"), Ok(Value::Integer(2))));
+    assert!(matches!(eval("6 | 3"), Ok(Value::Integer(7))));
+    assert!(matches!(eval("6 ^ 3"), Ok(Value::Integer(5))));
+    assert!(matches!(eval("~5"), Ok(Value::Integer(-6))));
+    assert!(matches!(eval("1 << 4"), Ok(Value::Integer(16))));
+    assert!(matches!(eval("-16 >> 2"), Ok(Value::Integer(-4))));
+}
+
+#[test]
+fn bitwise_operators_need_integers() {
+    assert_eq!(runtime_error("1.5 & 1;"), "Operands must be integers.");
+    assert_eq!(runtime_error("~\"a\";"), "Operand must be an integer.");
+}
+
+#[test]
+fn shift_amount_must_be_in_range() {
+    assert_eq!(runtime_error("1 << -1;"), "Shift amount must be non-negative.");
+    assert_eq!(runtime_error("1 >> -3;"), "Shift amount must be non-negative.");
+    assert_eq!(runtime_error("1 << 64;"), "Shift amount must be less than 64.");
+}
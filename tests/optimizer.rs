@@ -0,0 +1,58 @@
+// Folding constants must never change what a program does, only when the
+// work happens.
+
+mod common;
+
+use rlox::ast::Statement;
+use rlox::{optimizer, Parser, Resolver, Scanner};
+
+fn parse(source: &str) -> Vec<Statement> {
+    let tokens = Scanner::new(source.to_string()).scan_tokens().expect("source scans");
+    let (statements, errors) = Parser::new(tokens).parse();
+    assert!(errors.is_empty(), "{source:?} doesn't parse: {errors:?}");
+    statements
+}
+
+fn folded(source: &str) -> Vec<Statement> {
+    let mut statements = parse(source);
+    optimizer::optimize(&mut statements);
+    statements
+}
+
+// what running `statements` printed, then the error it failed with if any
+fn run(mut statements: Vec<Statement>) -> (String, Option<String>) {
+    Resolver::new().resolve(&mut statements);
+    let (printed, result) = common::run_statements(&statements);
+    (printed, result.err().map(|error| error.to_string()))
+}
+
+fn same_either_way(source: &str) {
+    assert_eq!(run(folded(source)), run(parse(source)), "folding changed what {source:?} does");
+}
+
+fn printed(statements: &[Statement]) -> Vec<String> {
+    statements.iter().map(Statement::print).collect()
+}
+
+#[test]
+fn folds_literal_subexpressions() {
+    assert_eq!(printed(&folded("print 2 + 3 * 4; print !true; print \"a\" + \"b\" + \"c\"; print -(1.5);")),
+        ["(print 14)", "(print false)", "(print \"abc\")", "(print -1.5)"]);
+    assert_eq!(printed(&folded("var x = 1; print x + 2 * 3;")), ["(var x 1)", "(print (+ x 6))"]);
+}
+
+#[test]
+fn folded_programs_print_the_same() {
+    same_either_way("print 2 + 3 * 4; print !true; print \"a\" + \"b\"; print 7 ~/ 2; print 1 < 2 == true;");
+    same_either_way("var x = 4; print x * (2 + 3); print (1 + 2) * x;");
+    same_either_way("print 0.1 + 0.2; print 10 / 4; print 1 << 3 | 1; print nil == false;");
+    same_either_way("if (1 + 1 == 2) print \"yes\"; else print \"no\";");
+}
+
+#[test]
+fn failing_expressions_fail_the_same() {
+    same_either_way("print 1; print 1 / 0; print 2;");
+    same_either_way("print \"a\" - 1;");
+    same_either_way("print 1 << -1;");
+    assert_eq!(printed(&folded("print 1 / 0;")), ["(print (/ 1 0))"]);
+}
@@ -0,0 +1,135 @@
+use rlox::ast::{Expression, Statement};
+use rlox::token::Span;
+use rlox::{ParseError, Parser, Scanner};
+
+fn parse(source: &str) -> (Vec<Statement>, Vec<ParseError>) {
+    let tokens = Scanner::new(source.to_string()).scan_tokens()
+        .unwrap_or_else(|errors| panic!("{source:?} doesn't scan: {errors:?}"));
+    Parser::new(tokens).parse()
+}
+
+fn statements(source: &str) -> Vec<Statement> {
+    match parse(source) {
+        (statements, errors) if errors.is_empty() => statements,
+        (_, errors) => panic!("{source:?} doesn't parse: {errors:?}")
+    }
+}
+
+fn errors(source: &str) -> Vec<String> {
+    parse(source).1.iter().map(ParseError::to_string).collect()
+}
+
+fn expression(source: &str) -> Expression {
+    match statements(&format!("{source};")).as_slice() {
+        [Statement::Expression(expression)] => expression.clone(),
+        statements => panic!("expected an expression, got {statements:?}")
+    }
+}
+
+#[test]
+fn function_declaration_with_two_parameters() {
+    match statements("fun add(a, b) { print a + b; }").as_slice() {
+        [Statement::Function(function)] => {
+            assert_eq!(function.name.lexeme, "add");
+            let params: Vec<_> = function.params.iter().map(|param| param.lexeme.as_str()).collect();
+            assert_eq!(params, ["a", "b"]);
+            assert_eq!(function.body.len(), 1);
+        },
+        statements => panic!("expected a function, got {statements:?}")
+    }
+}
+
+#[test]
+fn unclosed_parameter_list_is_an_error() {
+    assert_eq!(errors("fun add(a, b { }"), ["[line 1, col 14] Error at '{': Expect ')' after parameters."]);
+}
+
+#[test]
+fn rpn_printer_puts_operators_after_operands() {
+    assert_eq!(expression("(1 + 2) * 3").print_rpn(), "1 2 + 3 *");
+    assert_eq!(expression("(1 + 2) * (4 - 3)").print_rpn(), "1 2 + 4 3 - *");
+    assert_eq!(expression("-a + f(b, 2)").print_rpn(), "a - f b 2 call +");
+}
+
+#[test]
+fn dot_output_has_one_edge_per_child() {
+    let dot = expression("(1 + 2) * 3").to_dot();
+    assert!(dot.starts_with("digraph ast {\n") && dot.ends_with("}\n"));
+    // *, group, +, 1, 2 and 3, joined by one edge less than there are nodes
+    let edges = dot.matches(" -> ").count();
+    assert_eq!(dot.matches(" [label=").count() - edges, 6);
+    assert_eq!(edges, 5);
+    assert!(dot.contains("  n0 -> n1 [label=\"left\"];\n"));
+    assert!(dot.contains("  n0 -> n5 [label=\"right\"];\n"));
+}
+
+#[test]
+fn recovers_to_report_every_syntax_error() {
+    let (statements, errors) = parse("var = 1;\nprint 2;\nvar b = (3;\nprint b;");
+    let errors: Vec<_> = errors.iter().map(ParseError::to_string).collect();
+    assert_eq!(errors, [
+        "[line 1, col 5] Error at '=': Expect variable name.",
+        "[line 3, col 11] Error at ';': Expect ')' after expression."
+    ]);
+    assert_eq!(statements.len(), 2, "both prints survive synchronization");
+}
+
+#[test]
+fn nested_subexpressions_keep_their_own_span() {
+    let Expression::Binary(sum) = expression("1 +\n  (2 * foo)") else { panic!("expected a sum") };
+    assert_eq!(sum.left.span(), Span { line: 1, column: 1 });
+    let Expression::Grouping(group) = *sum.right else { panic!("expected a group") };
+    assert_eq!(group.paren.span(), Span { line: 2, column: 3 });
+    assert_eq!(group.expression.span(), Span { line: 2, column: 4 });
+    let Expression::Binary(product) = *group.expression else { panic!("expected a product") };
+    assert_eq!(product.right.span(), Span { line: 2, column: 8 });
+}
+
+#[test]
+fn tree_printer_indents_children_under_their_parent() {
+    assert_eq!(expression("(1 + 2) * -f(x)").print_tree(), "\
+*
+  group
+    +
+      1
+      2
+  -
+    call
+      f
+      x
+");
+
+    let [statement] = statements("if (a) print b + 1;").try_into().expect("one statement");
+    assert_eq!(statement.print_tree(), "\
+if
+  a
+  print
+    +
+      b
+      1
+");
+}
+
+#[test]
+fn calls_allow_a_trailing_comma() {
+    let Expression::Call(call) = expression("f(1, 2,)") else { panic!("expected a call") };
+    assert_eq!(call.arguments.len(), 2);
+    let Expression::List(list) = expression("[1, 2,]") else { panic!("expected a list") };
+    assert_eq!(list.elements.len(), 2);
+}
+
+#[test]
+fn a_lone_comma_is_not_an_argument() {
+    assert_eq!(errors("f(,);"), ["[line 1, col 3] Error at ',': Expect expression."]);
+    assert_eq!(errors("f(1,,2);"), ["[line 1, col 5] Error at ',': Expect expression."]);
+}
+
+#[test]
+fn required_parameters_cannot_follow_optional_ones() {
+    assert_eq!(errors("fun f(a = 1, b) {}"), ["[line 1, col 14] Error at 'b': Can't have a required parameter after an optional one."]);
+}
+
+#[test]
+fn rest_parameter_must_be_last() {
+    assert_eq!(errors("fun f(...rest, last) {}"), ["[line 1, col 14] Error at ',': Rest parameter must be last."]);
+}
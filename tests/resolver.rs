@@ -0,0 +1,46 @@
+mod common;
+
+use common::{output, resolve, runtime_error};
+
+#[test]
+fn reading_a_local_in_its_own_initializer_is_an_error() {
+    assert!(resolve("{ var a = a; }").is_none());
+    // globals are late bound, so it's a runtime matter there
+    assert_eq!(runtime_error("var b = b;"), "Undefined variable 'b'.");
+}
+
+#[test]
+fn redeclaring_a_local_is_an_error() {
+    assert!(resolve("{ var a = 1; var a = 2; }").is_none());
+    assert_eq!(output("var a = 1; var a = 2; print a;"), "2\n");
+}
+
+#[test]
+fn closures_see_the_variable_in_scope_where_they_were_declared() {
+    let source = "var a = \"global\"; { fun show() { print a; } show(); var a = \"block\"; show(); print a; }";
+    assert_eq!(output(source), "global\nglobal\nblock\n");
+}
+
+// each name resolves to the slot of the nearest declaration, even when the
+// shadowed one sits at a different index in its scope
+#[test]
+fn shadowed_variables_use_their_own_slots() {
+    let source = "
+        var a = \"global a\";
+        {
+            var b = \"outer b\";
+            var a = \"outer a\";
+            {
+                var a = \"inner a\";
+                a = a + \"!\";
+                b = b + \"?\";
+                print a;
+            }
+            print a;
+            print b;
+            fun f(b) { var a = b * 2; { var b = a + 1; print b; } return a; }
+            print f(5);
+        }
+        print a;";
+    assert_eq!(output(source), "inner a!\nouter a\nouter b?\n11\n10\nglobal a\n");
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, Read, Write};
+use std::rc::Rc;
+
+use common::{error, resolve, with_deep_stack};
+use rlox::{eval_with, Interpreter, LoxError, RuntimeError};
+
+fn runtime_error(source: &str) -> RuntimeError {
+    error(source).kind
+}
+
+fn name(name: &str) -> String {
+    name.to_string()
+}
+
+#[test]
+fn type_mismatch() {
+    assert_eq!(runtime_error("\"a\" - 1;"), RuntimeError::TypeMismatch("Operands must be numbers."));
+}
+
+#[test]
+fn division_by_zero() {
+    assert_eq!(runtime_error("1 / 0;"), RuntimeError::DivisionByZero);
+}
+
+#[test]
+fn invalid_shift() {
+    assert!(matches!(runtime_error("1 << -1;"), RuntimeError::InvalidShift(_)));
+}
+
+#[test]
+fn undefined_variable() {
+    assert_eq!(runtime_error("print nope;"), RuntimeError::UndefinedVariable { name: name("nope") });
+}
+
+#[test]
+fn undefined_property() {
+    assert_eq!(runtime_error("class A {} A().nope;"), RuntimeError::UndefinedProperty { name: name("nope") });
+}
+
+#[test]
+fn assign_to_native() {
+    assert_eq!(runtime_error("clock = 1;"), RuntimeError::AssignToNative { name: name("clock") });
+}
+
+#[test]
+fn arity_mismatch() {
+    assert!(matches!(runtime_error("fun f(a) {} f();"), RuntimeError::ArityMismatch { got: 0, .. }));
+}
+
+#[test]
+fn not_callable() {
+    assert_eq!(runtime_error("\"f\"();"), RuntimeError::NotCallable);
+}
+
+#[test]
+fn no_properties() {
+    assert_eq!(runtime_error("3.x;"), RuntimeError::NoProperties);
+}
+
+#[test]
+fn no_fields() {
+    assert_eq!(runtime_error("3.x = 1;"), RuntimeError::NoFields);
+}
+
+#[test]
+fn not_indexable() {
+    assert_eq!(runtime_error("3[0];"), RuntimeError::NotIndexable);
+}
+
+#[test]
+fn index_out_of_bounds() {
+    assert_eq!(runtime_error("[1][1];"), RuntimeError::IndexOutOfBounds);
+}
+
+#[test]
+fn superclass_not_class() {
+    assert_eq!(runtime_error("var A = 1; class B < A {}"), RuntimeError::SuperclassNotClass);
+}
+
+#[test]
+fn stack_overflow() {
+    assert_eq!(with_deep_stack(|| runtime_error("fun f() { return 1 + f(); } f();")), RuntimeError::StackOverflow);
+}
+
+#[test]
+fn empty_list() {
+    assert_eq!(runtime_error("pop([]);"), RuntimeError::EmptyList);
+}
+
+#[test]
+fn invalid_number() {
+    assert_eq!(runtime_error("num(\"abc\");"), RuntimeError::InvalidNumber { text: name("abc") });
+}
+
+#[test]
+fn assertion_failed() {
+    assert_eq!(runtime_error("assert(false);"), RuntimeError::AssertionFailed(None));
+    assert_eq!(runtime_error("assert(1 > 2, \"too small\");"), RuntimeError::AssertionFailed(Some(name("too small"))));
+}
+
+#[test]
+fn native_errors_report_the_call_line() {
+    assert_eq!(error("var a = [];\npop(a);").line, 2);
+}
+
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("broken pipe"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Err(io::Error::other("broken pipe"))
+    }
+}
+
+impl Read for Broken {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("unplugged"))
+    }
+}
+
+impl BufRead for Broken {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Err(io::Error::other("unplugged"))
+    }
+
+    fn consume(&mut self, _: usize) {}
+}
+
+fn kind(result: Result<rlox::Value, LoxError>) -> RuntimeError {
+    match result {
+        Err(LoxError::Runtime(error)) => error.kind,
+        other => panic!("expected a runtime error, got {other:?}")
+    }
+}
+
+#[test]
+fn output() {
+    let mut interpreter = Interpreter::with_writer(Rc::new(RefCell::new(Broken)));
+    assert_eq!(kind(eval_with(&mut interpreter, "print 1;")), RuntimeError::Output(name("broken pipe")));
+}
+
+#[test]
+fn input() {
+    let out = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = Interpreter::with_io(Broken, out);
+    assert_eq!(kind(eval_with(&mut interpreter, "input(\"> \");")), RuntimeError::Input(name("unplugged")));
+}
+
+// the REPL echoing an expression's value fails at that expression
+#[test]
+fn echo_output() {
+    let mut interpreter = Interpreter::with_writer(Rc::new(RefCell::new(Broken)));
+    let statements = resolve("var a = 1;\n\na + 1;").expect("source resolves");
+    let error = interpreter.interpret(&statements, true).expect_err("the echo fails");
+    assert_eq!(error.kind, RuntimeError::Output(name("broken pipe")));
+    assert_eq!((error.line, error.column), (3, Some(1)));
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use common::{error, output, with_deep_stack};
+use rlox::{eval_with, Interpreter, LoxError, RuntimeError, Value};
+
+#[test]
+fn deep_tail_recursion_runs_in_constant_stack() {
+    // far past max_call_depth, and on a test thread's 2 MiB stack
+    let source = "fun count(n, total) { if (n == 0) return total; return count(n - 1, total + n); } print count(100000, 0);";
+    assert_eq!(output(source), "5000050000\n");
+}
+
+#[test]
+fn mutual_tail_recursion_runs_in_constant_stack() {
+    let source = "
+        fun even(n) { if (n == 0) return true; return odd(n - 1); }
+        fun odd(n) { if (n == 0) return false; return even(n - 1); }
+        print even(100001);";
+    assert_eq!(output(source), "false\n");
+}
+
+#[test]
+fn calls_not_in_return_position_still_overflow() {
+    let source = "fun count(n) { if (n == 0) return 0; return 1 + count(n - 1); } count(100000);";
+    assert_eq!(with_deep_stack(move || error(source).kind), RuntimeError::StackOverflow);
+}
+
+#[test]
+fn trace_counts_the_frames_tail_calls_replaced() {
+    let source = "fun count(n) {\n  if (n == 0) return nil + 1;\n  return count(n - 1);\n}\ncount(3);";
+    let error = error(source);
+    assert_eq!(error.trace.len(), 1);
+    assert_eq!(error.trace[0].tail_calls, 3);
+    assert_eq!(error.to_string(), "[line 2, col 26] Runtime error: Operands must be two numbers or two strings.\n  in count() at line 3 (after 3 tail calls)");
+}
+
+// nothing counts tail calls, so unbounded tail recursion runs like a
+// `while (true)` loop until something else stops it, here a native failing
+// on its 100000th call
+#[test]
+fn unbounded_tail_recursion_never_overflows() {
+    let mut interpreter = Interpreter::new();
+    interpreter.max_call_depth = 10;
+    let calls = Rc::new(Cell::new(0));
+    let counted = Rc::clone(&calls);
+    interpreter.define_native("tick", 0, move |_| {
+        counted.set(counted.get() + 1);
+        if counted.get() == 100_000 { Err(RuntimeError::Other("stop".to_string())) } else { Ok(Value::Null) }
+    });
+    match eval_with(&mut interpreter, "fun f() { tick(); return f(); } f();") {
+        Err(LoxError::Runtime(error)) => assert_eq!(error.kind, RuntimeError::Other("stop".to_string())),
+        other => panic!("expected the native to stop it, got {other:?}")
+    }
+    assert_eq!(calls.get(), 100_000);
+}
@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rlox::{eval, Value};
+
+fn list(values: Vec<Value>) -> Value {
+    Value::List(Rc::new(RefCell::new(values)))
+}
+
+#[test]
+fn display_is_the_lox_form() {
+    assert_eq!(format!("{}", Value::Number(3.0)), "3");
+    assert_eq!(format!("{}", Value::Number(2.5)), "2.5");
+    assert_eq!(format!("{}", Value::Integer(-7)), "-7");
+    assert_eq!(format!("{}", Value::Boolean(true)), "true");
+    assert_eq!(format!("{}", Value::Null), "nil");
+    assert_eq!(format!("{}", Value::String("hi".to_string())), "hi");
+    assert_eq!(format!("{}", list(vec![Value::Integer(1), Value::String("a".to_string())])), "[1, a]");
+}
+
+#[test]
+fn debug_names_the_variant() {
+    assert_eq!(format!("{:?}", Value::Number(3.0)), "Number(3.0)");
+    assert_eq!(format!("{:?}", list(vec![Value::Null, Value::String("a".to_string())])), "List([Null, String(\"a\")])");
+}
+
+fn containing_itself() -> Value {
+    let value = list(vec![Value::Integer(1)]);
+    if let Value::List(l) = &value {
+        l.borrow_mut().push(value.clone());
+    }
+    value
+}
+
+// a list or map shows where it recurs instead of recursing forever
+#[test]
+fn self_containing_values_format() {
+    let value = containing_itself();
+    assert_eq!(format!("{value}"), "[1, [...]]");
+    assert_eq!(format!("{value:?}"), "List([Integer(1), List([...])])");
+    assert_eq!(format!("{}", list(vec![value.clone(), value])), "[[1, [...]], [1, [...]]]");
+
+    let map = Value::Map(Rc::new(RefCell::new(HashMap::new())));
+    if let Value::Map(m) = &map {
+        m.borrow_mut().insert("self".to_string(), map.clone());
+    }
+    assert_eq!(format!("{map}"), "{self: {...}}");
+    assert_eq!(format!("{map:?}"), "Map({\"self\": Map({...})})");
+
+    let instance = eval("class A {} var a = A(); a.me = a; a").expect("evaluates");
+    assert!(format!("{instance:?}").contains("Instance(...)"));
+}
+
+#[test]
+fn equality_of_like_variants() {
+    assert_eq!(Value::Boolean(true), Value::Boolean(true));
+    assert_ne!(Value::Boolean(true), Value::Boolean(false));
+    assert_eq!(Value::Null, Value::Null);
+    assert_eq!(Value::String("a".to_string()), Value::String("a".to_string()));
+    assert_ne!(Value::String("a".to_string()), Value::String("b".to_string()));
+    assert_eq!(Value::Integer(2), Value::Integer(2));
+    assert_eq!(Value::Number(2.5), Value::Number(2.5));
+    assert_ne!(Value::Number(f64::NAN), Value::Number(f64::NAN));
+}
+
+#[test]
+fn integers_equal_the_same_float() {
+    assert_eq!(Value::Integer(2), Value::Number(2.0));
+    assert_eq!(Value::Number(2.0), Value::Integer(2));
+    assert_ne!(Value::Integer(2), Value::Number(2.5));
+    assert_eq!(eval("1 + 1").expect("evaluates"), Value::Number(2.0));
+}
+
+#[test]
+fn different_variants_are_never_equal() {
+    assert_ne!(Value::Null, Value::Boolean(false));
+    assert_ne!(Value::Integer(0), Value::Boolean(false));
+    assert_ne!(Value::Integer(1), Value::String("1".to_string()));
+    assert_ne!(Value::Null, list(vec![]));
+}
+
+// lists, maps, functions, classes and instances are equal only to themselves
+#[test]
+fn reference_variants_compare_by_identity() {
+    let shared = list(vec![Value::Integer(1)]);
+    assert_eq!(shared, shared.clone());
+    assert_ne!(list(vec![Value::Integer(1)]), list(vec![Value::Integer(1)]));
+
+    let map = Value::Map(Rc::new(RefCell::new(HashMap::new())));
+    assert_eq!(map, map.clone());
+    assert_ne!(map, Value::Map(Rc::new(RefCell::new(HashMap::new()))));
+
+    // so comparing never looks inside, even at a list containing itself
+    let value = containing_itself();
+    assert_eq!(value, value.clone());
+    assert_ne!(value, containing_itself());
+
+    assert_eq!(eval("fun f() {} f == f").expect("evaluates"), Value::Boolean(true));
+    assert_eq!(eval("fun f() {} fun g() {} f == g").expect("evaluates"), Value::Boolean(false));
+    assert_eq!(eval("class A {} A == A").expect("evaluates"), Value::Boolean(true));
+    assert_eq!(eval("class A {} A() == A()").expect("evaluates"), Value::Boolean(false));
+    assert_eq!(eval("class A {} var a = A(); a == a").expect("evaluates"), Value::Boolean(true));
+    assert_eq!(eval("clock == clock").expect("evaluates"), Value::Boolean(true));
+    assert_eq!(eval("clock == len").expect("evaluates"), Value::Boolean(false));
+}
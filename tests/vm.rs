@@ -0,0 +1,173 @@
+// Runs programs through both backends, which must print the same and fail
+// with the same error at the same place.
+
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rlox::vm::{self, Vm};
+use rlox::{Interpreter, Parser, Resolver, Scanner};
+
+// what a backend printed, then its error and trace if it failed
+type Outcome = (String, Option<String>);
+
+fn both(source: &str) -> (Outcome, Outcome) {
+    let tokens = Scanner::new(source.to_string()).scan_tokens().expect("source scans");
+    let (mut statements, errors) = Parser::new(tokens).parse();
+    assert!(errors.is_empty(), "{source:?} doesn't parse: {errors:?}");
+    let mut resolver = Resolver::new();
+    resolver.resolve(&mut statements);
+    assert!(resolver.errors.is_empty(), "{source:?} doesn't resolve: {:?}", resolver.errors);
+
+    let out = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = Interpreter::with_writer(Rc::clone(&out));
+    let result = interpreter.interpret(&statements, false);
+    let tree_walker = outcome(&out, result);
+
+    let chunk = vm::compile(&statements, false).unwrap_or_else(|error| panic!("{source:?} doesn't compile: {error}"));
+    let out = Rc::new(RefCell::new(Vec::new()));
+    let mut vm = Vm::with_writer(Rc::clone(&out));
+    let result = vm.run(chunk);
+    (tree_walker, outcome(&out, result))
+}
+
+fn outcome(out: &RefCell<Vec<u8>>, result: Result<(), rlox::InterpreterError>) -> Outcome {
+    let printed = String::from_utf8(out.borrow().clone()).expect("output is utf-8");
+    (printed, result.err().map(|error| format!("{error} {:?}", error.trace)))
+}
+
+// both backends agree, and print `expected`
+fn same(source: &str, expected: &str) {
+    let (tree_walker, vm) = both(source);
+    assert_eq!(tree_walker, vm, "the backends disagree on {source:?}");
+    assert_eq!(tree_walker.0, expected);
+    assert_eq!(tree_walker.1, None, "{source:?} failed");
+}
+
+// both backends fail the same way, with `message`
+fn same_error(source: &str, message: &str) {
+    let (tree_walker, vm) = both(source);
+    assert_eq!(tree_walker, vm, "the backends disagree on {source:?}");
+    let error = tree_walker.1.unwrap_or_else(|| panic!("{source:?} succeeded"));
+    assert!(error.contains(message), "{error:?} doesn't mention {message:?}");
+}
+
+fn vm_compile_error(source: &str) -> String {
+    let tokens = Scanner::new(source.to_string()).scan_tokens().expect("source scans");
+    let (mut statements, _) = Parser::new(tokens).parse();
+    Resolver::new().resolve(&mut statements);
+    match vm::compile(&statements, false) {
+        Ok(_) => panic!("{source:?} compiled"),
+        Err(error) => error.message
+    }
+}
+
+#[test]
+fn arithmetic_and_variables() {
+    same("var a = 1; { var b = a + 2; a = b * 3; } print a; print 7 ~/ 2; print 1.5 + 1;", "9\n3\n2.5\n");
+    same("print 2 ** 10; print 6 & 3 | 8 ^ 1; print 1 << 4 >> 2; print ~0; print -(3);", "1024\n11\n4\n-1\n-3\n");
+    same("print \"a\" + \"b\"; print \"a\" < \"b\"; print 1 == 1.0; print nil != false; print !nil;", "ab\ntrue\ntrue\ntrue\ntrue\n");
+}
+
+#[test]
+fn control_flow() {
+    same("for (var i = 0; i < 5; i += 1) { if (i == 1) continue; if (i == 4) break; print i; }", "0\n2\n3\n");
+    same("var n = 0; while (n < 3) n += 1; print n;", "3\n");
+    same("switch (2) { case 1: print \"one\"; case 2: print \"two\"; default: print \"other\"; }", "two\n");
+    same("switch (\"x\") { case 1: print \"one\"; default: print \"other\"; }", "other\n");
+    same("print nil or \"b\"; print 1 and 2; print false ? 1 : 2;", "b\n2\n2\n");
+}
+
+#[test]
+fn recursive_functions() {
+    same("fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); } print fib(15);", "610\n");
+}
+
+#[test]
+fn functions_are_values() {
+    same("fun f() {} print f; print type(f); print f == f; var g = f; print g();", "<fn f>\nfunction\ntrue\nnil\n");
+}
+
+#[test]
+fn local_functions_and_early_returns() {
+    same("{ fun twice(x) { return x * 2; } print twice(4); }", "8\n");
+    same("fun find(n) { for (var i = 0; ; i += 1) { if (i * i >= n) return i; } } print find(50);", "8\n");
+}
+
+#[test]
+fn default_and_rest_parameters() {
+    same("fun greet(name, greeting = \"hi\") { return greeting + \" \" + name; } print greet(\"a\"); print greet(\"b\", \"yo\");", "hi a\nyo b\n");
+    same("fun sum(first = 0, ...rest) { for (var i = 0; i < len(rest); i += 1) first += rest[i]; return first; } print sum(); print sum(1); print sum(1, 2, 3);", "0\n1\n6\n");
+    same("fun g(a, b = a + 1, ...rest) { return [a, b, rest]; } print g(1); print g(1, 5, 6);", "[1, 2, []]\n[1, 5, [6]]\n");
+}
+
+#[test]
+fn tail_calls_run_in_constant_stack() {
+    same("fun count(n) { if (n == 0) return \"done\"; return count(n - 1); } print count(5000);", "done\n");
+}
+
+#[test]
+fn natives() {
+    same("print str(42) + \"!\"; print num(\"3.5\"); print len(\"héllo\"); print sqrt(16); print abs(-3); print pow(2, 10);", "42!\n3.5\n5\n4\n3\n1024\n");
+    same("print clock() > 0; var clock = 5; print clock;", "true\n5\n");
+}
+
+#[test]
+fn lists_and_maps() {
+    same("var l = [1, 2]; l[0] += 10; push(l, 3); print l; print pop(l); print len(l); print l[1];", "[11, 2, 3]\n3\n2\n2\n");
+    same("var m = {\"a\": 1}; m[\"b\"] = 2; m[\"a\"] *= 5; print m; print m[\"c\"]; print keys(m);", "{a: 5, b: 2}\nnil\n[a, b]\n");
+}
+
+#[test]
+fn runtime_errors() {
+    same_error("print 1 + nil;", "Operands must be two numbers or two strings.");
+    same_error("print 1 / 0;", "Division by zero.");
+    same_error("print undefined;", "Undefined variable 'undefined'.");
+    same_error("clock = 1;", "Can't assign to native function 'clock'.");
+    same_error("print -\"a\";", "Operand must be a number.");
+    same_error("print 1 << 64;", "Shift amount must be less than 64.");
+    same_error("3();", "Can only call functions and classes.");
+    same_error("print [1][5];", "List index out of bounds.");
+    same_error("print 1[0];", "Only lists and maps can be indexed.");
+    same_error("var m = {1: 2};", "Map keys must be strings.");
+    same_error("var l = [1]; l[0] += nil;", "Operands must be two numbers or two strings.");
+}
+
+#[test]
+fn call_errors() {
+    same_error("fun f(a) {}\nf(1, 2);", "Expected 1 argument but got 2.");
+    same_error("fun f(a, b = 1) {}\nf();", "Expected 1 to 2 arguments but got 0.");
+    same_error("len(1, 2);", "Expected 1 argument but got 2.");
+    same_error("pop([]);", "Can't pop from an empty list.");
+    same_error("assert(false, \"boom\");", "boom");
+}
+
+#[test]
+fn errors_carry_the_same_trace() {
+    same_error("fun a() { b(); }\nfun b() { return c(); }\nfun c() { assert(false); }\na();", "Assertion failed.");
+    same_error("fun f(n) {\n  if (n > 0) return f(n - 1);\n  return 1 + nil;\n}\nf(3);", "Operands must be two numbers or two strings.");
+}
+
+// the tree-walker needs Rust stack for max_call_depth calls, the VM keeps
+// its frames on the heap
+#[test]
+fn unbounded_recursion_overflows() {
+    let (tree_walker, vm) = common::with_deep_stack(|| both("fun f() { return 1 + f(); } f();"));
+    for (_, error) in [tree_walker, vm] {
+        assert!(error.is_some_and(|error| error.starts_with("[line 1] Runtime error: Stack overflow.")));
+    }
+}
+
+#[test]
+fn output_before_an_error_is_kept() {
+    let (tree_walker, vm) = both("print 1; print nil + 1; print 2;");
+    assert_eq!(tree_walker, vm);
+    assert_eq!(vm.0, "1\n");
+}
+
+#[test]
+fn closures_and_classes_are_not_compiled() {
+    assert_eq!(vm_compile_error("fun outer() { var x = 1; fun inner() { return x; } return inner; }"), "Closures aren't supported by the VM yet.");
+    assert_eq!(vm_compile_error("class A {}"), "Classes aren't supported by the VM yet.");
+}